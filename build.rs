@@ -1,10 +1,13 @@
 use std::process::Command;
 
 fn main() {
-    let output = Command::new("git")
-        .args(&["describe", "--long", "--dirty", "--tags"])
+    // Git isn't available when building from a crates.io package, so fall
+    // back to an empty description instead of failing the build.
+    let commit = Command::new("git")
+        .args(["describe", "--long", "--dirty", "--tags"].iter())
         .output()
-        .unwrap();
-    let commit = String::from_utf8(output.stdout).unwrap();
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
     println!("cargo:rustc-env=GIT_DESCRIPTION={}", commit);
 }
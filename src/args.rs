@@ -1,11 +1,17 @@
 use clap::Arg;
 
+use crate::output::OutputFormat;
+use crate::security::SASL_MECHANISMS;
+
 pub fn brokers<'a, 'n>() -> Arg<'a, 'n> {
-    // TODO: brokers should allow multiple values
     Arg::with_name("brokers")
         .short("b")
         .long("--brokers")
-        .help("Kafka brokers. Specify multiple times for multiple brokers.")
+        .help("Kafka brokers as host:port. Specify multiple times or separate with commas for multiple brokers.")
+        .validator(|v| crate::check_broker_address(v.trim()))
+        .multiple(true)
+        .number_of_values(1)
+        .use_delimiter(true)
         .global(true)
         .takes_value(true)
 }
@@ -19,6 +25,186 @@ pub fn zookeeper<'a, 'b>() -> Arg<'a, 'b> {
         .takes_value(true)
 }
 
+pub fn output<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .short("o")
+        .long("--output")
+        .help("Output format. Defaults to table if stdout is a terminal, json otherwise.")
+        .possible_values(OutputFormat::VALUES)
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn context<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("context")
+        .long("--context")
+        .help("Name of the context from the config file to use, instead of the current one (see `krs env list`)")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn timeout<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("timeout")
+        .long("--timeout")
+        .help("Timeout for each request to the cluster, e.g. 500ms, 30s, 5m (defaults to 5s). For `wait`, how long to wait overall (waits forever if not specified).")
+        .takes_value(true)
+        .global(true)
+}
+
+pub fn retries<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("retries")
+        .long("--retries")
+        .help("How many times to retry requests to the cluster that fail or time out, with backoff (defaults to 0)")
+        .takes_value(true)
+        .global(true)
+        .validator(|v| {
+            v.parse::<u32>()
+                .map(|_| ())
+                .map_err(|_| format!("Expected a non-negative number, but got `{}`", v))
+        })
+}
+
+pub fn verbose<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("verbose")
+        .short("v")
+        .long("--verbose")
+        .help("Print more diagnostics to stderr, including librdkafka's logs. Specify twice for even more.")
+        .multiple(true)
+        .global(true)
+}
+
+pub fn quiet<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("quiet")
+        .short("q")
+        .long("--quiet")
+        .help("Only print errors to stderr")
+        .conflicts_with("verbose")
+        .global(true)
+}
+
+pub fn debug<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("debug")
+        .long("--debug")
+        .help("Enable librdkafka debug contexts and print their logs, e.g. broker,protocol,fetch")
+        .value_name("contexts")
+        .possible_values(crate::logging::DEBUG_CONTEXTS)
+        .multiple(true)
+        .number_of_values(1)
+        .use_delimiter(true)
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn context_name<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("context_name")
+        .help("Name of the context from the config file")
+        .takes_value(true)
+}
+
+pub fn env_keys<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("keys")
+        .help("Keys to remove, e.g. brokers or KRS_BROKERS")
+        .value_name("key")
+        .multiple(true)
+        .takes_value(true)
+}
+
+pub fn tls<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls")
+        .long("--tls")
+        .help("Connect to the brokers with TLS (implied by --tls-ca-file, --tls-cert-file and --tls-key-file)")
+        .global(true)
+}
+
+pub fn tls_ca_file<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls_ca_file")
+        .long("--tls-ca-file")
+        .help("CA certificate file (PEM) to verify the brokers' certificates with")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn tls_cert_file<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls_cert_file")
+        .long("--tls-cert-file")
+        .help("Client certificate file (PEM), for brokers that require client authentication")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn tls_key_file<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls_key_file")
+        .long("--tls-key-file")
+        .help("Client private key file (PEM)")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn tls_key_password<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls_key_password")
+        .long("--tls-key-password")
+        .help("Password of the client private key")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn tls_no_verify_hostname<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls_no_verify_hostname")
+        .long("--tls-no-verify-hostname")
+        .help("Do not check that the brokers' certificates match their hostnames")
+        .global(true)
+}
+
+pub fn sasl_mechanism<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sasl_mechanism")
+        .long("--sasl-mechanism")
+        .help("Authenticate with SASL using this mechanism")
+        .possible_values(SASL_MECHANISMS)
+        .case_insensitive(true)
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn sasl_username<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sasl_username")
+        .long("--sasl-username")
+        .help("SASL username (the principal for OAUTHBEARER)")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn sasl_password_file<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sasl_password_file")
+        .long("--sasl-password-file")
+        .help("Read the SASL password from this file")
+        .conflicts_with("sasl_password_cmd")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn sasl_password_cmd<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sasl_password_cmd")
+        .long("--sasl-password-cmd")
+        .visible_alias("password-cmd")
+        .help("Run this shell command to get the SASL password, e.g. \"pass show kafka\"")
+        .global(true)
+        .takes_value(true)
+}
+
+pub fn rdkafka_property<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("rdkafka_property")
+        .short("X")
+        .help("Set a librdkafka property, e.g. fetch.max.bytes=1048576. Prefix it with consumer., producer. or admin. to only set it for that type of client. Specify multiple times for multiple properties.")
+        .validator(|v| match v.find('=') {
+            Some(i) if i > 0 => Ok(()),
+            _ => Err(format!("Expected key=value, but got `{}`", v)),
+        })
+        .multiple(true)
+        .number_of_values(1)
+        .global(true)
+        .takes_value(true)
+}
+
 pub fn topic<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("topic")
         .short("t")
@@ -51,3 +237,152 @@ pub fn num_replicas<'a, 'b>() -> Arg<'a, 'b> {
         .default_value("1")
         .takes_value(true)
 }
+
+pub fn config_entries<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("entries")
+        .help("Config entries to set, in the form of key=value")
+        .value_name("key=value")
+        .multiple(true)
+        .takes_value(true)
+}
+
+pub fn config_keys<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("keys")
+        .help("Config keys to unset (revert to default)")
+        .value_name("key")
+        .multiple(true)
+        .takes_value(true)
+}
+
+pub fn total_partitions<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("total")
+        .long("--total")
+        .help("Total number of partitions after the operation")
+        .takes_value(true)
+}
+
+pub fn config<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("config")
+        .long("--config")
+        .help("Topic config in the form of key=value. Specify multiple times for multiple configs.")
+        .value_name("key=value")
+        .multiple(true)
+        .number_of_values(1)
+        .takes_value(true)
+}
+
+pub fn replica_assignment<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("replica_assignment")
+        .long("--replica-assignment")
+        .help("Comma-separated list of colon-separated broker IDs for each partition, e.g. `0:1,1:2`. Overrides --num-partitions and --num-replicas.")
+        .takes_value(true)
+}
+
+pub fn if_not_exists<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("if_not_exists")
+        .long("--if-not-exists")
+        .help("Succeed even if the topic already exists")
+}
+
+pub fn validate_only<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("validate_only")
+        .long("--validate-only")
+        .help("Only validate the request, without actually applying it")
+}
+
+pub fn regex<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("regex")
+        .long("--regex")
+        .help("Regular expression to match topic names against")
+        .takes_value(true)
+}
+
+pub fn yes<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("yes")
+        .short("y")
+        .long("--yes")
+        .help("Do not ask for confirmation")
+}
+
+pub fn exclude_internal<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("exclude_internal")
+        .long("--exclude-internal")
+        .help("Hide internal topics, e.g. __consumer_offsets, _schemas, __confluent.*")
+}
+
+pub fn min_partitions<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("min_partitions")
+        .long("--min-partitions")
+        .help("Only list topics with at least this many partitions")
+        .takes_value(true)
+}
+
+pub fn sort<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sort")
+        .long("--sort")
+        .help("Sort topics by name, or by number of partitions (descending)")
+        .possible_values(&["name", "partitions"])
+        .takes_value(true)
+}
+
+pub fn with_counts<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("with_counts")
+        .long("--with-counts")
+        .help("Also show the number of messages in each topic (slower, since it fetches watermarks of every partition)")
+}
+
+pub fn broker_id<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("broker")
+        .long("--broker")
+        .help("Broker ID")
+        .takes_value(true)
+}
+
+pub fn all_brokers<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("all")
+        .long("--all")
+        .help("Apply to all brokers in the cluster. Sets per-broker overrides on each broker, not the cluster-wide default.")
+        .conflicts_with("broker")
+}
+
+pub fn min_brokers<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("min_brokers")
+        .long("--min-brokers")
+        .help("Wait until at least this many brokers are in the cluster")
+        .default_value("1")
+        .takes_value(true)
+}
+
+pub fn wait_group<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("wait_group")
+        .long("--group")
+        .help(
+            "Wait until the consumer group is stable. Specify multiple times for multiple groups.",
+        )
+        .multiple(true)
+        .number_of_values(1)
+        .takes_value(true)
+}
+
+pub fn lag_below<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("lag_below")
+        .long("--lag-below")
+        .help("Wait until the total lag of the consumer group specified with -g/--group-id is below this (over the topics specified with -t, or all topics the group has committed offsets for)")
+        .takes_value(true)
+}
+
+pub fn message_matching<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("message_matching")
+        .long("--message-matching")
+        .help("After the other conditions are met, wait until a record whose key, payload or a header value matches this regular expression shows up in the topics specified with -t, then print it")
+        .requires("topic")
+        .takes_value(true)
+}
+
+pub fn start_offset<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("start_offset")
+        .long("--from")
+        .help("Where to start looking for the record in each partition: beginning (default), end, or an offset")
+        .requires("message_matching")
+        .takes_value(true)
+}
@@ -0,0 +1,362 @@
+use std::convert::TryFrom;
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_int;
+use std::time::Duration;
+
+use clap::{App, SubCommand};
+use rdkafka::admin::{AdminClient, ConfigSource, ResourceSpecifier};
+use rdkafka::client::Client;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::metadata::Metadata;
+use rdkafka_sys as rdsys;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zookeeper::ZooKeeper;
+
+use crate::args;
+use crate::commands::configs::{
+    apply_alter_config, describe_config, plan_alter_config, ConfigEntryInfo,
+};
+use crate::{
+    new_admin_client, new_client, new_consumer, new_zookeeper, Config, Error, LoggingContext,
+    OutputFormat, RetryPolicy,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BrokerInfo {
+    id: i32,
+    host: String,
+    port: i32,
+    // Only available from Zookeeper.
+    rack: Option<String>,
+    is_controller: Option<bool>,
+}
+
+fn timeout_ms(timeout: Duration) -> c_int {
+    timeout.as_millis().min(c_int::max_value() as u128) as c_int
+}
+
+// Reads a JSON znode, e.g. `/controller` or `/brokers/ids/1`.
+pub(crate) fn get_zk_json(zk: &ZooKeeper, retry: &RetryPolicy, path: &str) -> Option<Value> {
+    match retry.run(&format!("get {} from Zookeeper", path), |_| {
+        zk.get_data(path, false)
+    }) {
+        Ok((data, _)) => serde_json::from_slice(&data).ok(),
+        Err(e) => {
+            log::warn!("Failed to get {} from Zookeeper: {:?}", path, e);
+            None
+        }
+    }
+}
+
+// Asks librdkafka first, which also works without Zookeeper (e.g. on KRaft or
+// managed clusters), and falls back to the `/cluster/id` znode.
+pub(crate) fn get_cluster_id(
+    client: &Client<LoggingContext>,
+    zk: Option<&ZooKeeper>,
+    retry: &RetryPolicy,
+) -> Option<String> {
+    let ptr = unsafe { rdsys::rd_kafka_clusterid(client.native_ptr(), timeout_ms(retry.timeout)) };
+    if !ptr.is_null() {
+        let id = unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { rdsys::rd_kafka_mem_free(client.native_ptr(), ptr as *mut c_void) };
+        return Some(id);
+    }
+    zk.and_then(|zk| get_zk_json(zk, retry, "/cluster/id"))
+        .and_then(|v| v.get("id").and_then(Value::as_str).map(str::to_owned))
+}
+
+// Same as `get_cluster_id`, with the `/controller` znode as the fallback.
+pub(crate) fn get_controller_id(
+    client: &Client<LoggingContext>,
+    zk: Option<&ZooKeeper>,
+    retry: &RetryPolicy,
+) -> Option<i32> {
+    let id =
+        unsafe { rdsys::rd_kafka_controllerid(client.native_ptr(), timeout_ms(retry.timeout)) };
+    if id >= 0 {
+        return Some(id);
+    }
+    zk.and_then(|zk| get_zk_json(zk, retry, "/controller"))
+        .and_then(|v| v.get("brokerid").and_then(Value::as_i64))
+        .map(|id| id as i32)
+}
+
+// Takes the controller from `get_controller_id`, which callers might need
+// themselves, since looking it up can take a full timeout.
+pub(crate) fn get_broker_infos(
+    md: &Metadata,
+    controller: Option<i32>,
+    zk: Option<&ZooKeeper>,
+    retry: &RetryPolicy,
+) -> Vec<BrokerInfo> {
+    let mut infos: Vec<BrokerInfo> = md
+        .brokers()
+        .iter()
+        .map(|b| BrokerInfo {
+            id: b.id(),
+            host: b.host().to_owned(),
+            port: b.port(),
+            rack: zk
+                .and_then(|zk| get_zk_json(zk, retry, &format!("/brokers/ids/{}", b.id())))
+                .and_then(|v| v.get("rack").and_then(Value::as_str).map(str::to_owned)),
+            is_controller: controller.map(|c| c == b.id()),
+        })
+        .collect();
+    infos.sort_by_key(|b| b.id);
+    infos
+}
+
+// Zookeeper is only used to enrich the output, so failing to connect is not
+// an error.
+pub(crate) fn maybe_connect_zookeeper(conf: &Config, retry: &RetryPolicy) -> Option<ZooKeeper> {
+    conf.zookeeper.as_ref().and_then(|zookeeper| {
+        new_zookeeper(zookeeper, retry)
+            .map_err(|e| log::warn!("Failed to connect to Zookeeper at {}: {:?}", zookeeper, e))
+            .ok()
+    })
+}
+
+pub struct ListCommand {
+    client: Client<LoggingContext>,
+    zk: Option<ZooKeeper>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ListCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("list")
+            .about("List brokers in the cluster")
+            .long_about("List brokers in the cluster.\n\nIf Zookeeper is specified and reachable, the output also includes each broker's rack.")
+    }
+
+    pub fn run(&self) -> crate::Result<()> {
+        let md = self.retry.run("fetch metadata", |timeout| {
+            self.client.fetch_metadata(None, Some(timeout))
+        })?;
+
+        let controller = get_controller_id(&self.client, self.zk.as_ref(), &self.retry);
+        self.output.print(&get_broker_infos(
+            &md,
+            controller,
+            self.zk.as_ref(),
+            &self.retry,
+        ))
+    }
+}
+
+impl TryFrom<Config> for ListCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf
+            .brokers
+            .as_ref()
+            .ok_or_else(|| Error::InvalidUsage("brokers is required for `brokers list`".into()))?;
+
+        let retry = conf.retry_policy()?;
+        Ok(Self {
+            client: new_client(&conf, brokers)?,
+            zk: maybe_connect_zookeeper(&conf, &retry),
+            retry,
+            output: conf.output_format(),
+        })
+    }
+}
+
+pub struct ConfigGetCommand {
+    admin: AdminClient<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ConfigGetCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("get")
+            .about(
+                "Shows the configs of the specified broker, including where each value comes from.",
+            )
+            .arg(args::broker_id().required(true))
+    }
+
+    pub fn run(&self, broker_id: i32) -> crate::Result<()> {
+        let resource = describe_config(
+            &self.admin,
+            &self.retry,
+            ResourceSpecifier::Broker(broker_id),
+        )?;
+        let infos: Vec<ConfigEntryInfo> = resource.entries.iter().map(|e| e.into()).collect();
+
+        self.output.print(&infos)
+    }
+}
+
+impl TryFrom<Config> for ConfigGetCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `brokers config get`".into())
+        })?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
+
+/// Which brokers to change the dynamic configs of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrokerSelection {
+    One(i32),
+    // Every broker in the cluster, one by one.
+    All,
+}
+
+// Alters the dynamic configs of the selected brokers one by one, and returns
+// the IDs of the brokers that were altered.
+fn alter_broker_configs(
+    admin: &AdminClient<LoggingContext>,
+    consumer: &BaseConsumer<LoggingContext>,
+    retry: &RetryPolicy,
+    selection: BrokerSelection,
+    set: &[(String, String)],
+    unset: &[String],
+) -> crate::Result<Vec<i32>> {
+    let broker_ids = match selection {
+        BrokerSelection::One(id) => vec![id],
+        BrokerSelection::All => {
+            let md = retry.run("fetch metadata", |timeout| {
+                consumer.fetch_metadata(None, Some(timeout))
+            })?;
+            md.brokers().iter().map(|b| b.id()).collect()
+        }
+    };
+
+    // Check all brokers before altering any of them, so that e.g. a sensitive
+    // config on one broker doesn't leave the others half-done.
+    let mut plans = Vec::new();
+    for &id in broker_ids.iter() {
+        let specifier = ResourceSpecifier::Broker(id);
+        let entries = plan_alter_config(
+            admin,
+            retry,
+            specifier,
+            &ConfigSource::DynamicBroker,
+            set,
+            unset,
+        )?;
+        plans.push((specifier, entries));
+    }
+    for (specifier, entries) in plans.iter() {
+        apply_alter_config(admin, retry, *specifier, entries)?;
+    }
+    Ok(broker_ids)
+}
+
+pub struct ConfigSetCommand {
+    admin: AdminClient<LoggingContext>,
+    // Only used to list brokers for `--all`.
+    consumer: BaseConsumer<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ConfigSetCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("set")
+            .about("Sets dynamic configs of the specified broker(s).")
+            .arg(args::broker_id().required_unless("all"))
+            .arg(args::all_brokers())
+            .arg(args::config_entries().required(true))
+    }
+
+    pub fn run(
+        &self,
+        selection: BrokerSelection,
+        entries: &[(String, String)],
+    ) -> crate::Result<()> {
+        let altered = alter_broker_configs(
+            &self.admin,
+            &self.consumer,
+            &self.retry,
+            selection,
+            entries,
+            &[],
+        )?;
+
+        // Print broker IDs only if successful
+        self.output.print(&altered)
+    }
+}
+
+impl TryFrom<Config> for ConfigSetCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `brokers config set`".into())
+        })?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            consumer: new_consumer(&conf, brokers, None)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
+
+pub struct ConfigUnsetCommand {
+    admin: AdminClient<LoggingContext>,
+    // Only used to list brokers for `--all`.
+    consumer: BaseConsumer<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ConfigUnsetCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("unset")
+            .about("Removes dynamic configs of the specified broker(s).")
+            .arg(args::broker_id().required_unless("all"))
+            .arg(args::all_brokers())
+            .arg(args::config_keys().required(true))
+    }
+
+    pub fn run(&self, selection: BrokerSelection, keys: &[String]) -> crate::Result<()> {
+        let altered = alter_broker_configs(
+            &self.admin,
+            &self.consumer,
+            &self.retry,
+            selection,
+            &[],
+            keys,
+        )?;
+
+        // Print broker IDs only if successful
+        self.output.print(&altered)
+    }
+}
+
+impl TryFrom<Config> for ConfigUnsetCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `brokers config unset`".into())
+        })?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            consumer: new_consumer(&conf, brokers, None)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
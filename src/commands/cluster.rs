@@ -0,0 +1,84 @@
+use std::convert::TryFrom;
+
+use clap::{App, SubCommand};
+use rdkafka::client::Client;
+use serde::{Deserialize, Serialize};
+use zookeeper::ZooKeeper;
+
+use crate::commands::brokers::{
+    get_broker_infos, get_cluster_id, get_controller_id, maybe_connect_zookeeper, BrokerInfo,
+};
+use crate::commands::topics::{is_offline, is_under_replicated};
+use crate::{new_client, Config, Error, LoggingContext, OutputFormat, RetryPolicy};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClusterInfo {
+    cluster_id: Option<String>,
+    controller: Option<i32>,
+    num_brokers: usize,
+    num_topics: usize,
+    num_partitions: usize,
+    num_under_replicated_partitions: usize,
+    num_offline_partitions: usize,
+    brokers: Vec<BrokerInfo>,
+}
+
+pub struct DescribeCommand {
+    client: Client<LoggingContext>,
+    zk: Option<ZooKeeper>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl DescribeCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("describe")
+            .about("Show an overview of the cluster.")
+            .long_about("Show an overview of the cluster.\n\nThe cluster ID and the controller come from the brokers, or from Zookeeper if specified and the brokers are too old to report them. Racks are only available from Zookeeper.")
+    }
+
+    pub fn run(&self) -> crate::Result<()> {
+        let md = self.retry.run("fetch metadata", |timeout| {
+            self.client.fetch_metadata(None, Some(timeout))
+        })?;
+        let partitions: Vec<_> = md.topics().iter().flat_map(|t| t.partitions()).collect();
+        let controller = get_controller_id(&self.client, self.zk.as_ref(), &self.retry);
+
+        let info = ClusterInfo {
+            cluster_id: get_cluster_id(&self.client, self.zk.as_ref(), &self.retry),
+            controller,
+            num_brokers: md.brokers().len(),
+            num_topics: md.topics().len(),
+            num_partitions: partitions.len(),
+            num_under_replicated_partitions: partitions
+                .iter()
+                .filter(|p| is_under_replicated(p))
+                .count(),
+            num_offline_partitions: partitions
+                .iter()
+                .filter(|p| is_offline(p, md.brokers()))
+                .count(),
+            brokers: get_broker_infos(&md, controller, self.zk.as_ref(), &self.retry),
+        };
+
+        self.output.print(&info)
+    }
+}
+
+impl TryFrom<Config> for DescribeCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `cluster describe`".into())
+        })?;
+
+        let retry = conf.retry_policy()?;
+        Ok(Self {
+            client: new_client(&conf, brokers)?,
+            zk: maybe_connect_zookeeper(&conf, &retry),
+            retry,
+            output: conf.output_format(),
+        })
+    }
+}
@@ -0,0 +1,217 @@
+use std::convert::TryFrom;
+use std::io::Write;
+
+use clap::{App, AppSettings, Arg, Shell, SubCommand};
+use rdkafka::consumer::{BaseConsumer, Consumer};
+
+use crate::{make_visible_parser, new_consumer, Config, Error, LoggingContext, RetryPolicy};
+
+// The scripts generated by clap only complete subcommands and options, so
+// topic names and group IDs are completed by calling `krs __complete`, which
+// uses the current context. Errors (e.g. no brokers) are hidden by the
+// scripts, so that completion just doesn't offer anything.
+
+// Wraps the `_krs` function generated by clap.
+const BASH_DYNAMIC: &str = r#"
+_krs_dynamic() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "${prev}" in
+        -t|--topic)
+            COMPREPLY=($(compgen -W "$(krs __complete topics 2>/dev/null)" -- "${cur}"))
+            return 0
+            ;;
+        -g|--group-id|--group)
+            COMPREPLY=($(compgen -W "$(krs __complete groups 2>/dev/null)" -- "${cur}"))
+            return 0
+            ;;
+    esac
+    _krs "$@"
+}
+
+complete -F _krs_dynamic -o bashdefault -o default krs
+"#;
+
+// Used as the actions of the topic and group options, see `zsh_actions`.
+const ZSH_DYNAMIC: &str = r#"
+_krs_topics() {
+    local -a topics
+    topics=(${(f)"$(krs __complete topics 2>/dev/null)"})
+    compadd -a topics
+}
+
+_krs_groups() {
+    local -a groups
+    groups=(${(f)"$(krs __complete groups 2>/dev/null)"})
+    compadd -a groups
+}
+"#;
+
+const FISH_DYNAMIC: &str = r#"
+complete -c krs -n "__fish_seen_subcommand_from topics consumer producer wait" -s t -l topic -x -a "(krs __complete topics 2>/dev/null)"
+complete -c krs -s g -l group-id -x -a "(krs __complete groups 2>/dev/null)"
+complete -c krs -n "__fish_seen_subcommand_from wait" -l group -x -a "(krs __complete groups 2>/dev/null)"
+"#;
+
+// clap leaves the values of the topic and group options without an action,
+// e.g. `'-t+[Kafka topic name]' \`.
+fn zsh_actions(script: &str) -> String {
+    script
+        .lines()
+        .map(|line| {
+            let unquoted = line.trim_start_matches("'*").trim_start_matches('\'');
+            let action = if unquoted.starts_with("-t+[") || unquoted.starts_with("--topic=[") {
+                "_krs_topics"
+            } else if unquoted.starts_with("-g+[")
+                || unquoted.starts_with("--group-id=[")
+                || unquoted.starts_with("--group=[")
+            {
+                "_krs_groups"
+            } else {
+                return line.to_owned();
+            };
+            match line.rfind("]' \\") {
+                Some(i) => format!("{}]: :{}{}", &line[..i], action, &line[i + 1..]),
+                None => line.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Completion script for the given shell, with dynamic completion of topic
+/// names and group IDs (except for PowerShell and Elvish).
+pub fn script(shell: Shell) -> String {
+    let mut buf = Vec::new();
+    make_visible_parser().gen_completions_to("krs", shell, &mut buf);
+    let script = String::from_utf8_lossy(&buf);
+    match shell {
+        Shell::Bash => format!("{}{}", script, BASH_DYNAMIC),
+        // The functions have to be defined before `_krs "$@"` at the end.
+        Shell::Zsh => match script.find('\n') {
+            Some(i) => format!(
+                "{}{}{}\n",
+                &script[..=i],
+                ZSH_DYNAMIC,
+                zsh_actions(&script[i + 1..])
+            ),
+            None => script.into_owned(),
+        },
+        Shell::Fish => format!("{}{}", script, FISH_DYNAMIC),
+        Shell::PowerShell | Shell::Elvish => script.into_owned(),
+    }
+}
+
+pub struct CompletionsCommand;
+
+impl CompletionsCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("completions")
+            .about("Prints a shell completion script.")
+            .long_about("Prints a shell completion script, which also completes topic names and group IDs from the current context (except for PowerShell and Elvish).\n\nE.g. for bash:\n\n    krs completions bash > ~/.local/share/bash-completion/completions/krs")
+            .arg(
+                Arg::with_name("shell")
+                    .help("Shell to print the completion script for")
+                    .possible_values(&Shell::variants())
+                    .required(true)
+                    .takes_value(true),
+            )
+    }
+
+    pub fn run(&self, shell: Shell) -> crate::Result<()> {
+        std::io::stdout().write_all(script(shell).as_bytes())?;
+        Ok(())
+    }
+}
+
+/// What `krs __complete` lists, one per line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletionKind {
+    Topics,
+    Groups,
+}
+
+impl CompletionKind {
+    pub const VALUES: &'static [&'static str] = &["topics", "groups"];
+}
+
+impl std::str::FromStr for CompletionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "topics" => Ok(CompletionKind::Topics),
+            "groups" => Ok(CompletionKind::Groups),
+            _ => Err(Error::InvalidUsage(format!(
+                "Expected one of {}, but got `{}`",
+                Self::VALUES.join(", "),
+                s
+            ))),
+        }
+    }
+}
+
+// Called by the completion scripts, so it's hidden from `--help`.
+pub struct CompleteCommand {
+    consumer: BaseConsumer<LoggingContext>,
+    retry: RetryPolicy,
+}
+
+impl CompleteCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("__complete")
+            .about("Lists topic names or group IDs for shell completion.")
+            .setting(AppSettings::Hidden)
+            .arg(
+                Arg::with_name("kind")
+                    .possible_values(CompletionKind::VALUES)
+                    .required(true)
+                    .takes_value(true),
+            )
+    }
+
+    pub fn run(&self, kind: CompletionKind) -> crate::Result<()> {
+        let mut names: Vec<String> = match kind {
+            CompletionKind::Topics => self
+                .retry
+                .run("fetch metadata", |timeout| {
+                    self.consumer.fetch_metadata(None, Some(timeout))
+                })?
+                .topics()
+                .iter()
+                .map(|t| t.name().to_owned())
+                .collect(),
+            CompletionKind::Groups => self
+                .retry
+                .run("fetch groups", |timeout| {
+                    self.consumer.fetch_group_list(None, Some(timeout))
+                })?
+                .groups()
+                .iter()
+                .map(|g| g.name().to_owned())
+                .collect(),
+        };
+        names.sort();
+        for name in names {
+            println!("{}", name);
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<Config> for CompleteCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf
+            .brokers
+            .as_ref()
+            .ok_or_else(|| Error::InvalidUsage("brokers is required for `__complete`".into()))?;
+
+        Ok(Self {
+            consumer: new_consumer(&conf, brokers, None)?,
+            retry: conf.retry_policy()?,
+        })
+    }
+}
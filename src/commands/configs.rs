@@ -0,0 +1,167 @@
+// Helpers shared by the `config` subcommands of different Kafka resources
+// (topics, brokers).
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::future::Future;
+use rdkafka::admin::{
+    AdminClient, AdminOptions, AlterConfig, ConfigEntry, ConfigResource, ConfigSource,
+    ResourceSpecifier,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, LoggingContext, RetryPolicy};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ConfigEntryInfo {
+    name: String,
+    value: Option<String>,
+    source: String,
+    is_default: bool,
+    is_read_only: bool,
+    is_sensitive: bool,
+}
+
+impl From<&ConfigEntry> for ConfigEntryInfo {
+    fn from(e: &ConfigEntry) -> Self {
+        Self {
+            name: e.name.clone(),
+            value: e.value.clone(),
+            source: format!("{:?}", e.source),
+            is_default: e.is_default,
+            is_read_only: e.is_read_only,
+            is_sensitive: e.is_sensitive,
+        }
+    }
+}
+
+// Broker configs have to be described/altered on the broker itself.
+fn admin_options(specifier: ResourceSpecifier<'_>, timeout: Duration) -> AdminOptions {
+    let options = AdminOptions::new().request_timeout(Some(timeout));
+    match specifier {
+        ResourceSpecifier::Broker(id) => options.broker_id(id),
+        _ => options,
+    }
+}
+
+pub(crate) fn describe_config(
+    admin: &AdminClient<LoggingContext>,
+    retry: &RetryPolicy,
+    specifier: ResourceSpecifier<'_>,
+) -> crate::Result<ConfigResource> {
+    let rx = retry.run("describe configs", |timeout| {
+        admin
+            .describe_configs(&[specifier], &admin_options(specifier, timeout))
+            .wait()
+    })?;
+
+    rx.into_iter()
+        .next()
+        .ok_or_else(|| Error::Broker(format!("No configs returned for {:?}", specifier)))?
+        .map_err(|e| {
+            Error::from_code(
+                format!(
+                    "Failed to describe configs for {:?}. Reason: `{:?}`",
+                    specifier, e
+                ),
+                e,
+            )
+        })
+}
+
+// The AlterConfigs API replaces the whole set of dynamic configs of a
+// resource, so the entries that are currently set from `dynamic_source` have
+// to be sent along with the changed ones, or they'd be reverted to defaults.
+// Returns the entries to send.
+pub(crate) fn plan_alter_config(
+    admin: &AdminClient<LoggingContext>,
+    retry: &RetryPolicy,
+    specifier: ResourceSpecifier<'_>,
+    dynamic_source: &ConfigSource,
+    set: &[(String, String)],
+    unset: &[String],
+) -> crate::Result<HashMap<String, String>> {
+    let current = describe_config(admin, retry, specifier)?;
+
+    let mut entries = HashMap::new();
+    let mut hidden = Vec::new();
+    for e in current.entries.iter() {
+        if &e.source != dynamic_source {
+            continue;
+        }
+        match &e.value {
+            Some(v) => {
+                entries.insert(e.name.clone(), v.clone());
+            }
+            // Sensitive values (e.g. keystore passwords) aren't returned, so
+            // they can't be sent along, and would be wiped.
+            None if !set.iter().any(|(k, _)| k == &e.name) && !unset.contains(&e.name) => {
+                hidden.push(e.name.as_str())
+            }
+            None => (),
+        }
+    }
+    if !hidden.is_empty() {
+        return Err(Error::InvalidUsage(format!(
+            "Not altering configs of {:?}, since it would reset the sensitive config(s) `{}`, whose values can't be read back. Set them explicitly along with the other configs, or unset them.",
+            specifier,
+            hidden.join("`, `")
+        )));
+    }
+
+    for k in unset {
+        if entries.remove(k).is_none()
+            && !current
+                .entries
+                .iter()
+                .any(|e| &e.name == k && &e.source == dynamic_source)
+        {
+            log::warn!("`{}` is not set, nothing to unset.", k);
+        }
+    }
+    for (k, v) in set {
+        entries.insert(k.clone(), v.clone());
+    }
+    Ok(entries)
+}
+
+pub(crate) fn apply_alter_config(
+    admin: &AdminClient<LoggingContext>,
+    retry: &RetryPolicy,
+    specifier: ResourceSpecifier<'_>,
+    entries: &HashMap<String, String>,
+) -> crate::Result<()> {
+    let entries = entries
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let alter = [AlterConfig { specifier, entries }];
+    let rx = retry.run("alter configs", |timeout| {
+        admin
+            .alter_configs(&alter, &admin_options(specifier, timeout))
+            .wait()
+    })?;
+
+    rx.into_iter()
+        .next()
+        .ok_or_else(|| Error::Broker(format!("No result returned for {:?}", specifier)))?
+        .map(|_| ())
+        .map_err(|(s, e)| {
+            Error::from_code(
+                format!("Failed to alter configs for {:?}. Reason: `{:?}`", s, e),
+                e,
+            )
+        })
+}
+
+pub(crate) fn alter_config(
+    admin: &AdminClient<LoggingContext>,
+    retry: &RetryPolicy,
+    specifier: ResourceSpecifier<'_>,
+    dynamic_source: &ConfigSource,
+    set: &[(String, String)],
+    unset: &[String],
+) -> crate::Result<()> {
+    let entries = plan_alter_config(admin, retry, specifier, dynamic_source, set, unset)?;
+    apply_alter_config(admin, retry, specifier, &entries)
+}
@@ -1,16 +1,60 @@
 use std::convert::TryFrom;
 
+use chrono::offset::Utc;
 use clap::{App, SubCommand};
 use futures::stream::Stream;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::message::Message;
+use rdkafka::message::{Message, OwnedMessage};
+use serde::Serialize;
 use tokio::runtime::current_thread::Runtime;
 
 use crate::args;
-use crate::{new_consumer, Config};
+use crate::{new_consumer, Config, LoggingContext, OutputFormat};
 
 pub struct ConsumerCommand {
-    consumer: StreamConsumer,
+    consumer: StreamConsumer<LoggingContext>,
+    output: OutputFormat,
+}
+
+#[derive(Debug, Serialize)]
+struct ConsumedRecord {
+    topic: String,
+    partition: i32,
+    offset: i64,
+    timestamp: Option<i64>,
+    key: Option<String>,
+    payload: Option<String>,
+}
+
+impl From<&OwnedMessage> for ConsumedRecord {
+    fn from(msg: &OwnedMessage) -> Self {
+        Self {
+            topic: msg.topic().to_owned(),
+            partition: msg.partition(),
+            offset: msg.offset(),
+            timestamp: msg.timestamp().to_millis(),
+            key: msg.key().map(|k| String::from_utf8_lossy(k).into_owned()),
+            payload: msg
+                .payload()
+                .map(|p| String::from_utf8_lossy(p).into_owned()),
+        }
+    }
+}
+
+pub(crate) fn print_message(output: OutputFormat, msg: &OwnedMessage) {
+    if output != OutputFormat::Table {
+        if let Err(e) = output.print_record(&ConsumedRecord::from(msg)) {
+            log::error!("Failed to print message: {}", e);
+        }
+        return;
+    }
+
+    // Tables only show the payload, like the console consumer.
+    match msg.payload_view::<str>() {
+        Some(Ok(v)) => println!("{}", v),
+        Some(Err(_)) => log::warn!("Message payload is not a string."),
+        None => log::warn!("No message."),
+    };
 }
 
 impl ConsumerCommand {
@@ -23,30 +67,25 @@ impl ConsumerCommand {
     pub fn run(&self, topic_name: &str) -> crate::Result<()> {
         self.consumer.subscribe(&[topic_name])?;
 
+        let output = self.output;
         let pipeline = self
             .consumer
             .start()
             .filter_map(|r| match r {
                 Ok(msg) => Some(msg),
                 Err(e) => {
-                    println!("Error while receiving from Kafka: {:?}", e);
+                    log::warn!("Error while receiving from Kafka: {}", e);
                     None
                 }
             })
             .for_each(|msg| {
-                let msg = msg.detach();
-                match msg.payload_view::<str>() {
-                    Some(Ok(v)) => println!("{}", v),
-                    Some(Err(_)) => eprintln!("Message payload is not a string."),
-                    None => eprintln!("No message."),
-                };
+                print_message(output, &msg.detach());
                 Ok(())
             });
 
-        let mut rt = Runtime::new().unwrap();
+        let mut rt = Runtime::new()?;
         rt.block_on(pipeline)
-            .expect("Failed to start consumer pipeline");
-        Ok(())
+            .map_err(|_| crate::Error::Generic("Consumer pipeline failed".into()))
     }
 }
 
@@ -58,11 +97,17 @@ impl TryFrom<Config> for ConsumerCommand {
             crate::Error::InvalidUsage("brokers is required for `consumer`".into())
         })?;
 
-        let group_id = conf.group_id;
+        let output = conf.output_format();
+        // Subscribing needs a group ID, so make up a fresh one if not given.
+        let group_id = conf
+            .group_id
+            .as_ref()
+            .map(|g| g.value.to_owned())
+            .unwrap_or_else(|| format!("krs-{}", Utc::now().timestamp_millis()));
 
         Ok(Self {
-            // TODO: Can do group_id.as_deref() in Rust 1.40
-            consumer: new_consumer(&brokers, group_id.as_ref().map(String::as_str)),
+            consumer: new_consumer(&conf, brokers, Some(&group_id))?,
+            output,
         })
     }
 }
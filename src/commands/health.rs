@@ -0,0 +1,140 @@
+use std::convert::TryFrom;
+
+use clap::{App, SubCommand};
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::error::RDKafkaError;
+use rdkafka::metadata::{MetadataPartition, MetadataTopic};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::topics::{is_offline, is_under_replicated};
+use crate::{new_consumer, Config, Error, LoggingContext, OutputFormat, RetryPolicy};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PartitionProblem {
+    topic: String,
+    partition: i32,
+    leader: i32,
+    replicas: Vec<i32>,
+    isr: Vec<i32>,
+}
+
+impl PartitionProblem {
+    fn new(mt: &MetadataTopic, mp: &MetadataPartition) -> Self {
+        Self {
+            topic: mt.name().to_owned(),
+            partition: mp.id(),
+            leader: mp.leader(),
+            replicas: Vec::from(mp.replicas()),
+            isr: Vec::from(mp.isr()),
+        }
+    }
+}
+
+// The brokers returned an error instead of the topic's metadata, e.g. while
+// it's being created or deleted.
+#[derive(Debug, Serialize, Deserialize)]
+struct TopicError {
+    topic: String,
+    error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct HealthReport {
+    topic_errors: Vec<TopicError>,
+    under_replicated_partitions: Vec<PartitionProblem>,
+    offline_partitions: Vec<PartitionProblem>,
+    // The leader is not the first replica in the assignment, i.e. a preferred
+    // leader election is needed to rebalance leadership.
+    non_preferred_leader_partitions: Vec<PartitionProblem>,
+    single_replica_topics: Vec<String>,
+}
+
+impl HealthReport {
+    fn num_problems(&self) -> usize {
+        self.topic_errors.len()
+            + self.under_replicated_partitions.len()
+            + self.offline_partitions.len()
+            + self.non_preferred_leader_partitions.len()
+            + self.single_replica_topics.len()
+    }
+}
+
+pub struct HealthCommand {
+    consumer: BaseConsumer<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl HealthCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("health")
+            .about("Checks the cluster for unhealthy partitions and topics.")
+            .long_about("Checks the cluster for topics whose metadata can't be fetched, under-replicated partitions, offline partitions, partitions whose leader is not the preferred replica, and topics with a replication factor of 1.\n\nExits with a non-zero status if any problems are found.")
+    }
+
+    pub fn run(&self) -> crate::Result<()> {
+        let md = self.retry.run("fetch metadata", |timeout| {
+            self.consumer.fetch_metadata(None, Some(timeout))
+        })?;
+
+        let mut report = HealthReport::default();
+        for mt in md.topics() {
+            if let Some(e) = mt.error() {
+                report.topic_errors.push(TopicError {
+                    topic: mt.name().to_owned(),
+                    error: RDKafkaError::from(e).to_string(),
+                });
+                continue;
+            }
+            for mp in mt.partitions() {
+                if is_offline(mp, md.brokers()) {
+                    report
+                        .offline_partitions
+                        .push(PartitionProblem::new(mt, mp));
+                } else if mp.replicas().first() != Some(&mp.leader()) {
+                    report
+                        .non_preferred_leader_partitions
+                        .push(PartitionProblem::new(mt, mp));
+                }
+                if is_under_replicated(mp) {
+                    report
+                        .under_replicated_partitions
+                        .push(PartitionProblem::new(mt, mp));
+                }
+            }
+            // Topics without partitions (yet) don't have a replication factor.
+            if !mt.partitions().is_empty()
+                && mt.partitions().iter().all(|p| p.replicas().len() <= 1)
+            {
+                report.single_replica_topics.push(mt.name().to_owned());
+            }
+        }
+
+        self.output.print(&report)?;
+
+        match report.num_problems() {
+            0 => Ok(()),
+            n => Err(Error::Generic(format!(
+                "Found {} problem(s) in the cluster.",
+                n
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Config> for HealthCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf
+            .brokers
+            .as_ref()
+            .ok_or_else(|| Error::InvalidUsage("brokers is required for `health`".into()))?;
+
+        Ok(Self {
+            consumer: new_consumer(&conf, brokers, None)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
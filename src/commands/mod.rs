@@ -1,5 +1,10 @@
+pub mod brokers;
+pub mod cluster;
+pub mod completions;
+pub mod configs;
 pub mod consumer;
 pub mod env;
+pub mod health;
 pub mod producer;
 pub mod topics;
 pub mod wait;
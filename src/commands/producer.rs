@@ -6,10 +6,10 @@ use futures::stream::Stream;
 use rdkafka::producer::{FutureProducer, FutureRecord};
 
 use crate::args;
-use crate::{new_producer, Config, Error};
+use crate::{new_producer, Config, Error, LoggingContext};
 
 pub struct ProducerCommand {
-    producer: FutureProducer,
+    producer: FutureProducer<LoggingContext>,
 }
 
 fn prompt() -> impl Stream<Item = String, Error = std::io::Error> {
@@ -32,21 +32,21 @@ impl ProducerCommand {
 
         let fut = prompt()
             .fuse()
-            .map_err(|e| eprintln!("Error reading line from stdin: {:?}", e))
+            .map_err(|e| log::error!("Error reading line from stdin: {:?}", e))
             .for_each(move |line| {
                 producer
                     .send(FutureRecord::to(&topic_name).key("").payload(&line), 0)
                     .and_then(|r| {
                         match r {
-                            Ok(delivery) => eprintln!("Sent: {:?}", delivery),
-                            Err((e, _)) => eprintln!("Error: {:?}", e),
+                            Ok(delivery) => log::info!("Sent: {:?}", delivery),
+                            Err((e, _)) => log::error!("Failed to send: {:?}", e),
                         };
                         Ok(())
                     })
                     .map_err(|_| ())
             });
 
-        eprintln!("Starting console producer. Press Ctrl+C to exit.");
+        log::info!("Starting console producer. Press Ctrl+C to exit.");
         // Can't use current_thread::Runtime here because otherwise no one's
         // handling stdin.
         // https://stackoverflow.com/questions/57590175
@@ -65,7 +65,7 @@ impl TryFrom<Config> for ProducerCommand {
             .ok_or_else(|| Error::InvalidUsage("brokers is required for `producer`".into()))?;
 
         Ok(Self {
-            producer: new_producer(&brokers),
+            producer: new_producer(&conf, brokers)?,
         })
     }
 }
@@ -1,51 +1,100 @@
+use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::time::{Duration, Instant};
 
 use chrono::{TimeZone, Utc};
 use clap::{App, SubCommand};
 use futures::future::Future;
-use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
-use rdkafka::client::DefaultClientContext;
+use rdkafka::admin::{
+    AdminClient, AdminOptions, ConfigSource, NewPartitions, NewTopic, ResourceSpecifier,
+    TopicReplication,
+};
 use rdkafka::consumer::{BaseConsumer, Consumer};
-use rdkafka::metadata::MetadataTopic;
+use rdkafka::error::RDKafkaError;
+use rdkafka::message::Message;
+use rdkafka::metadata::{MetadataBroker, MetadataPartition, MetadataTopic};
+use rdkafka::{Offset, TopicPartitionList};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json;
-use zookeeper::{WatchedEvent, Watcher, ZooKeeper};
+use zookeeper::ZooKeeper;
 
 use crate::args;
-use crate::{new_admin_client, new_consumer, Config, Error, DEFAULT_TIMEOUT};
+use crate::commands::configs::{alter_config, describe_config, ConfigEntryInfo};
+use crate::{
+    new_admin_client, new_assigned_consumer, new_consumer, new_zookeeper, Config, Error,
+    LoggingContext, OutputFormat, RetryPolicy,
+};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 struct PartitionInfo {
     id: i32,
     watermarks: (i64, i64),
-    //leader: Option<i32>,
-    //replicas: Option<Vec<i32>>,
-    //isr: Option<Vec<i32>>,
+    leader: i32,
+    // host:port of the leader, None if the leader is not a known broker.
+    leader_broker: Option<String>,
+    replicas: Vec<i32>,
+    isr: Vec<i32>,
+    // No leader is currently available for this partition.
+    offline: bool,
+    // Some replicas are not in sync with the leader.
+    under_replicated: bool,
+}
+
+// Returns the address of the partition leader, if it is a known broker.
+fn leader_broker(mp: &MetadataPartition, brokers: &[MetadataBroker]) -> Option<String> {
+    brokers
+        .iter()
+        .find(|b| b.id() == mp.leader())
+        .map(|b| format!("{}:{}", b.host(), b.port()))
+}
+
+pub(crate) fn is_offline(mp: &MetadataPartition, brokers: &[MetadataBroker]) -> bool {
+    leader_broker(mp, brokers).is_none()
+}
+
+pub(crate) fn is_under_replicated(mp: &MetadataPartition) -> bool {
+    mp.isr().len() < mp.replicas().len()
+}
+
+impl PartitionInfo {
+    fn new(mp: &MetadataPartition, brokers: &[MetadataBroker]) -> Self {
+        Self {
+            id: mp.id(),
+            watermarks: (-1, -1),
+            leader: mp.leader(),
+            leader_broker: leader_broker(mp, brokers),
+            replicas: Vec::from(mp.replicas()),
+            isr: Vec::from(mp.isr()),
+            offline: is_offline(mp, brokers),
+            under_replicated: is_under_replicated(mp),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub(crate) struct TopicInfo {
     name: String,
-    partitions: Vec<PartitionInfo>,
+    // Sum of (high - low) watermarks over all partitions. Might be an
+    // overestimate for compacted or transactional topics.
+    num_messages: i64,
+    earliest_timestamp: Option<String>,
+    latest_timestamp: Option<String>,
     // Both the fields below are only retrieved in DescribeCommand
     ctime: Option<String>,
     mtime: Option<String>,
+    partitions: Vec<PartitionInfo>,
 }
 
-impl From<&MetadataTopic> for TopicInfo {
-    fn from(mt: &MetadataTopic) -> Self {
+impl TopicInfo {
+    // Needs the list of brokers from the same metadata response to resolve
+    // the partition leaders' addresses.
+    fn new(mt: &MetadataTopic, brokers: &[MetadataBroker]) -> Self {
         Self {
             name: mt.name().to_owned(),
             partitions: mt
                 .partitions()
                 .iter()
-                .map(|p| PartitionInfo {
-                    id: p.id(),
-                    watermarks: (-1, -1),
-                    //leader: Some(p.leader()),
-                    //replicas: Some(Vec::from(p.replicas())),
-                    //isr: Some(Vec::from(p.isr()))
-                })
+                .map(|p| PartitionInfo::new(p, brokers))
                 .collect(),
             ..Default::default()
         }
@@ -56,6 +105,9 @@ impl From<&MetadataTopic> for TopicInfo {
 pub(crate) struct ShortTopicInfo {
     pub name: String,
     pub num_partitions: usize,
+    // Only retrieved with `--with-counts`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_messages: Option<i64>,
     // TODO: Get from zookeeper.
     //replication_factor: usize
     //ctime: Instant,
@@ -67,27 +119,160 @@ impl From<&MetadataTopic> for ShortTopicInfo {
         Self {
             name: mt.name().to_owned(),
             num_partitions: mt.partitions().len(),
+            num_messages: None,
         }
     }
 }
 
+fn count_messages(
+    consumer: &BaseConsumer<LoggingContext>,
+    retry: &RetryPolicy,
+    mt: &MetadataTopic,
+) -> crate::Result<i64> {
+    let mut total = 0;
+    for p in mt.partitions() {
+        let (low, high) = retry.run("fetch watermarks", |timeout| {
+            consumer.fetch_watermarks(mt.name(), p.id(), Some(timeout))
+        })?;
+        total += high - low;
+    }
+    Ok(total)
+}
+
+// Reads the record at the given offset of each partition, and returns the
+// timestamps of the records that could be read before the deadline.
+fn fetch_timestamps(
+    consumer: &BaseConsumer<LoggingContext>,
+    deadline: Instant,
+    topic_name: &str,
+    offsets: &[(i32, i64)],
+) -> crate::Result<Vec<i64>> {
+    if offsets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut tpl = TopicPartitionList::new();
+    for &(partition, offset) in offsets {
+        tpl.add_partition_offset(topic_name, partition, Offset::Offset(offset));
+    }
+    consumer.assign(&tpl)?;
+
+    let mut remaining: HashSet<i32> = offsets.iter().map(|&(p, _)| p).collect();
+    let mut timestamps = Vec::new();
+    let mut error = None;
+    while error.is_none() && !remaining.is_empty() && Instant::now() < deadline {
+        match consumer.poll(Duration::from_millis(100)) {
+            Some(Ok(msg)) if remaining.remove(&msg.partition()) => {
+                timestamps.extend(msg.timestamp().to_millis());
+            }
+            Some(Ok(_)) | None => (),
+            Some(Err(e)) => error = Some(e),
+        }
+    }
+
+    // Unassign even if polling failed, since the consumer is reused.
+    consumer.assign(&TopicPartitionList::new())?;
+    match error {
+        Some(e) => Err(e.into()),
+        None => Ok(timestamps),
+    }
+}
+
+// Topics used internally by Kafka and the Confluent platform, e.g.
+// `__consumer_offsets`, `__confluent.support.metrics` or `_schemas`.
+pub(crate) fn is_internal_topic(name: &str) -> bool {
+    name.starts_with("__") || name == "_schemas"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TopicSort {
+    Name,
+    // Descending, so the biggest topics come first.
+    Partitions,
+}
+
+impl std::str::FromStr for TopicSort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "name" => Ok(TopicSort::Name),
+            "partitions" => Ok(TopicSort::Partitions),
+            _ => Err(Error::InvalidUsage(format!(
+                "Expected one of name|partitions for sort, but got `{}`",
+                s
+            ))),
+        }
+    }
+}
+
+/// Optional filters for `topics list`.
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    pub pattern: Option<Regex>,
+    pub exclude_internal: bool,
+    pub min_partitions: Option<usize>,
+    pub sort: Option<TopicSort>,
+    pub with_counts: bool,
+}
+
 pub struct ListCommand {
-    consumer: BaseConsumer,
+    consumer: BaseConsumer<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
 }
 
 impl ListCommand {
     pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name("list").about("List topics")
+        SubCommand::with_name("list")
+            .about("List topics")
+            .arg(args::regex().help("Only list topics whose names match this regex"))
+            .arg(args::exclude_internal())
+            .arg(args::min_partitions())
+            .arg(args::sort())
+            .arg(args::with_counts())
     }
 
-    pub fn run(&self) -> crate::Result<()> {
-        let md = self.consumer.fetch_metadata(None, Some(DEFAULT_TIMEOUT))?;
+    pub fn run(&self, options: &ListOptions) -> crate::Result<()> {
+        let md = self.retry.run("fetch metadata", |timeout| {
+            self.consumer.fetch_metadata(None, Some(timeout))
+        })?;
 
         let topics = md.topics();
-        let infos: Vec<ShortTopicInfo> = topics.iter().map(|t| t.into()).collect();
+        let mut infos = Vec::new();
+        for t in topics
+            .iter()
+            .filter(|t| !options.exclude_internal || !is_internal_topic(t.name()))
+            .filter(|t| {
+                options
+                    .pattern
+                    .as_ref()
+                    .map_or(true, |re| re.is_match(t.name()))
+            })
+            .filter(|t| {
+                options
+                    .min_partitions
+                    .map_or(true, |n| t.partitions().len() >= n)
+            })
+        {
+            let mut info = ShortTopicInfo::from(t);
+            if options.with_counts {
+                info.num_messages = Some(count_messages(&self.consumer, &self.retry, t)?);
+            }
+            infos.push(info);
+        }
+
+        match options.sort {
+            Some(TopicSort::Name) => infos.sort_by(|a, b| a.name.cmp(&b.name)),
+            Some(TopicSort::Partitions) => infos.sort_by(|a, b| {
+                b.num_partitions
+                    .cmp(&a.num_partitions)
+                    .then_with(|| a.name.cmp(&b.name))
+            }),
+            None => (),
+        }
 
-        println!("{}", serde_json::to_string(&infos).unwrap());
-        Ok(())
+        self.output.print(&infos)
     }
 }
 
@@ -98,56 +283,121 @@ impl TryFrom<Config> for ListCommand {
         let brokers = conf
             .brokers
             .as_ref()
-            .ok_or_else(|| Error::Generic("brokers is required for `topics list`".into()))?;
+            .ok_or_else(|| Error::InvalidUsage("brokers is required for `topics list`".into()))?;
         Ok(Self {
-            consumer: new_consumer(&brokers, None),
+            consumer: new_consumer(&conf, brokers, None)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
         })
     }
 }
 
-struct DoNothingWatcher;
-impl Watcher for DoNothingWatcher {
-    fn handle(&self, _e: WatchedEvent) {
-        // Do nothing
-    }
-}
-
 pub struct DescribeCommand {
-    consumer: BaseConsumer,
-    zk: ZooKeeper,
+    consumer: BaseConsumer<LoggingContext>,
+    // ZooKeeper is only used to enrich the output with ctime/mtime, so it's
+    // optional (e.g. KRaft clusters or managed Kafka without ZK access).
+    zk: Option<ZooKeeper>,
+    retry: RetryPolicy,
+    output: OutputFormat,
 }
 
 impl DescribeCommand {
     pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
         SubCommand::with_name("describe")
             .about("Show more info about the specified topic.")
+            .long_about("Show more info about the specified topic.\n\nIf Zookeeper is specified and reachable, the output also includes the topic's ctime/mtime.")
             .arg(args::topic().required(true))
     }
 
     pub fn run(&self, topic_name: &str) -> crate::Result<()> {
-        let md = self
-            .consumer
-            .fetch_metadata(Some(&topic_name), Some(DEFAULT_TIMEOUT))?;
-        let topics = md.topics();
-        assert!(topics.len() == 1, "DescribeCommand takes only 1 topic");
+        let md = self.retry.run("fetch metadata", |timeout| {
+            self.consumer
+                .fetch_metadata(Some(topic_name), Some(timeout))
+        })?;
+        let topic = md
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic_name)
+            .ok_or_else(|| {
+                Error::Broker(format!("No metadata returned for topic `{}`", topic_name))
+            })?;
+        if let Some(e) = topic.error() {
+            let code = RDKafkaError::from(e);
+            return Err(Error::from_code(
+                format!("Failed to describe topic `{}`: {}", topic_name, code),
+                code,
+            ));
+        }
 
-        let mut info = TopicInfo::from(&topics[0]);
+        let mut info = TopicInfo::new(topic, md.brokers());
         for p in info.partitions.iter_mut() {
-            let watermarks =
+            let watermarks = self.retry.run("fetch watermarks", |timeout| {
                 self.consumer
-                    .fetch_watermarks(&topic_name, p.id, Some(DEFAULT_TIMEOUT))?;
+                    .fetch_watermarks(topic_name, p.id, Some(timeout))
+            })?;
             p.watermarks = watermarks;
+            info.num_messages += watermarks.1 - watermarks.0;
         }
 
-        let (_, stat) = self
-            .zk
-            .get_data(&format!("/brokers/topics/{}", topic_name), false)
-            .unwrap();
-        info.ctime = Some(Utc.timestamp(stat.ctime / 1000, 0).to_string());
-        info.mtime = Some(Utc.timestamp(stat.mtime / 1000, 0).to_string());
+        let non_empty: Vec<&PartitionInfo> = info
+            .partitions
+            .iter()
+            .filter(|p| p.watermarks.1 > p.watermarks.0)
+            .collect();
+        let earliest = non_empty
+            .iter()
+            .map(|p| (p.id, p.watermarks.0))
+            .collect::<Vec<_>>();
+        let latest = non_empty
+            .iter()
+            .map(|p| (p.id, p.watermarks.1 - 1))
+            .collect::<Vec<_>>();
+        // Best-effort, since reading records needs more permissions than
+        // describing the topic (READ on the topic and the group), and some
+        // offsets have no readable record, e.g. transaction markers. Both
+        // lookups share a single timeout.
+        let deadline = Instant::now() + self.retry.timeout;
+        let timestamps = |offsets: &[(i32, i64)]| {
+            fetch_timestamps(&self.consumer, deadline, topic_name, offsets).unwrap_or_else(|e| {
+                log::warn!(
+                    "Failed to read records of `{}`, skipping their timestamps: {}",
+                    topic_name,
+                    e
+                );
+                Vec::new()
+            })
+        };
+        // Timestamps are set by producers, so they can be anything.
+        let to_datetime = |ts: i64| Utc.timestamp_millis_opt(ts).single();
+        info.earliest_timestamp = timestamps(&earliest)
+            .into_iter()
+            .filter_map(to_datetime)
+            .min()
+            .map(|t| t.to_string());
+        info.latest_timestamp = timestamps(&latest)
+            .into_iter()
+            .filter_map(to_datetime)
+            .max()
+            .map(|t| t.to_string());
+
+        if let Some(zk) = &self.zk {
+            let path = format!("/brokers/topics/{}", topic_name);
+            match self
+                .retry
+                .run("get topic from Zookeeper", |_| zk.get_data(&path, false))
+            {
+                Ok((_, stat)) => {
+                    info.ctime = Some(Utc.timestamp(stat.ctime / 1000, 0).to_string());
+                    info.mtime = Some(Utc.timestamp(stat.mtime / 1000, 0).to_string());
+                }
+                Err(e) => log::warn!(
+                    "Failed to get topic metadata from Zookeeper, skipping ctime/mtime: {:?}",
+                    e
+                ),
+            }
+        }
 
-        println!("{}", serde_json::to_string(&info).unwrap());
-        Ok(())
+        self.output.print(&info)
     }
 }
 
@@ -155,24 +405,69 @@ impl TryFrom<Config> for DescribeCommand {
     type Error = Error;
 
     fn try_from(conf: Config) -> crate::Result<Self> {
-        let brokers = conf
-            .brokers
-            .as_ref()
-            .ok_or_else(|| Error::Generic("brokers is required for `topics describe`".into()))?;
-        let zookeeper = conf
-            .zookeeper
-            .as_ref()
-            .ok_or_else(|| Error::Generic("zookeeper is required for `topics describe`".into()))?;
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `topics describe`".into())
+        })?;
+        let retry = conf.retry_policy()?;
+        let zk = conf.zookeeper.as_ref().and_then(|zookeeper| {
+            new_zookeeper(zookeeper, &retry)
+                .map_err(|e| {
+                    log::warn!(
+                        "Failed to connect to Zookeeper at {}, skipping ctime/mtime: {:?}",
+                        zookeeper,
+                        e
+                    )
+                })
+                .ok()
+        });
 
         Ok(Self {
-            consumer: new_consumer(&brokers, None),
-            zk: ZooKeeper::connect(&zookeeper, DEFAULT_TIMEOUT, DoNothingWatcher).unwrap(),
+            // Also used to read the first and last records for timestamps.
+            consumer: new_assigned_consumer(
+                &conf,
+                brokers,
+                conf.group_id.as_ref().map(|g| g.value.as_str()),
+            )?,
+            zk,
+            retry,
+            output: conf.output_format(),
         })
     }
 }
 
+/// Optional parameters for `topics create`.
+#[derive(Debug, Default)]
+pub struct CreateOptions {
+    pub configs: Vec<(String, String)>,
+    // Replica broker IDs for each partition. Overrides the number of
+    // partitions and replicas if specified.
+    pub replica_assignment: Option<Vec<Vec<i32>>>,
+    pub if_not_exists: bool,
+    pub validate_only: bool,
+}
+
+// Parses replica assignments in the same format as `kafka-topics`, e.g.
+// `0:1,1:2` assigns partition 0 to brokers 0 and 1, and partition 1 to
+// brokers 1 and 2.
+pub(crate) fn parse_replica_assignment(s: &str) -> crate::Result<Vec<Vec<i32>>> {
+    s.split(',')
+        .map(|partition| {
+            partition
+                .split(':')
+                .map(|id| {
+                    id.trim().parse::<i32>().map_err(|e| {
+                        Error::InvalidUsage(format!("Invalid replica assignment `{}`: {}", s, e))
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
 pub struct CreateCommand {
-    admin: AdminClient<DefaultClientContext>,
+    admin: AdminClient<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
 }
 
 impl CreateCommand {
@@ -182,6 +477,10 @@ impl CreateCommand {
             .arg(args::topic().required(true))
             .arg(args::num_partitions())
             .arg(args::num_replicas())
+            .arg(args::config())
+            .arg(args::replica_assignment())
+            .arg(args::if_not_exists())
+            .arg(args::validate_only())
     }
 
     pub fn run(
@@ -189,22 +488,66 @@ impl CreateCommand {
         topic_name: &str,
         num_partitions: i32,
         num_replicas: i32,
+        options: &CreateOptions,
     ) -> crate::Result<()> {
-        let new_topics = &[NewTopic::new(
-            topic_name,
-            num_partitions,
-            TopicReplication::Fixed(num_replicas),
-        )];
-        let admin_options = &AdminOptions::new();
-        let rx = self.admin.create_topics(new_topics, admin_options).wait()?;
-
-        rx[0]
+        let assignment: Option<Vec<&[i32]>> = options
+            .replica_assignment
             .as_ref()
-            // Print topic name only if successful
-            .map(|t| println!("{}", t))
-            .map_err(|(n, e)| {
-                Error::Generic(format!("Failed to create topic `{}`. Reason: `{:?}`", n, e))
-            })
+            .map(|a| a.iter().map(Vec::as_slice).collect());
+        let new_topic = match &assignment {
+            Some(a) => NewTopic::new(topic_name, a.len() as i32, TopicReplication::Variable(a)),
+            None => NewTopic::new(
+                topic_name,
+                num_partitions,
+                TopicReplication::Fixed(num_replicas),
+            ),
+        };
+        let new_topic = options
+            .configs
+            .iter()
+            .fold(new_topic, |t, (k, v)| t.set(k, v));
+
+        let new_topics = [new_topic];
+        // A request that timed out might still have created the topic.
+        let mut attempts = 0;
+        let rx = self.retry.run("create topic", |timeout| {
+            attempts += 1;
+            let admin_options = AdminOptions::new()
+                .validate_only(options.validate_only)
+                .request_timeout(Some(timeout));
+            self.admin.create_topics(&new_topics, &admin_options).wait()
+        })?;
+
+        match rx.first() {
+            None => Err(Error::Broker(format!(
+                "No result returned for topic `{}`",
+                topic_name
+            ))),
+            Some(Ok(t)) => {
+                if options.validate_only {
+                    log::info!("Validation succeeded, topic `{}` was not created.", t);
+                }
+                // Print topic name only if successful
+                self.output.print(t)
+            }
+            Some(Err((n, RDKafkaError::TopicAlreadyExists)))
+                if attempts > 1 && !options.validate_only =>
+            {
+                log::info!(
+                    "Topic `{}` already exists, probably from an earlier attempt.",
+                    n
+                );
+                self.output.print(n)
+            }
+            Some(Err((n, RDKafkaError::TopicAlreadyExists))) if options.if_not_exists => {
+                log::info!("Topic `{}` already exists.", n);
+                self.output.print(n)
+            }
+            Some(Err((n, e))) => Err(Error::from_code(
+                format!("Failed to create topic `{}`. Reason: `{:?}`", n, e),
+                *e,
+            )),
+        }
     }
 }
 
@@ -218,35 +561,208 @@ impl TryFrom<Config> for CreateCommand {
             .ok_or_else(|| Error::InvalidUsage("brokers is required for `create`".into()))?;
 
         Ok(Self {
-            admin: new_admin_client(&brokers),
+            admin: new_admin_client(&conf, brokers)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
+
+pub struct AddPartitionsCommand {
+    admin: AdminClient<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl AddPartitionsCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("add-partitions")
+            .about("Increases the number of partitions of the specified Kafka topic.")
+            .long_about("Increases the number of partitions of the specified Kafka topic.\n\nNote that this changes which partition a given key maps to, so records with the same key may end up in different partitions before and after.")
+            .arg(args::topic().required(true))
+            .arg(args::total_partitions().required(true))
+            .arg(args::replica_assignment().help(
+                "Comma-separated list of colon-separated broker IDs for each new partition, e.g. `0:1,1:2`.",
+            ))
+    }
+
+    pub fn run(
+        &self,
+        topic_name: &str,
+        total: usize,
+        replica_assignment: Option<&[Vec<i32>]>,
+    ) -> crate::Result<()> {
+        let assignment: Option<Vec<&[i32]>> =
+            replica_assignment.map(|a| a.iter().map(Vec::as_slice).collect());
+        let mut new_partitions = NewPartitions::new(topic_name, total);
+        if let Some(a) = &assignment {
+            new_partitions = new_partitions.assign(a);
+        }
+
+        log::warn!(
+            "Increasing the partition count changes the key-to-partition mapping for `{}`.",
+            topic_name
+        );
+        let new_partitions = [new_partitions];
+        // A request that timed out might still have added the partitions.
+        let mut attempts = 0;
+        let rx = self.retry.run("add partitions", |timeout| {
+            attempts += 1;
+            let admin_options = AdminOptions::new().request_timeout(Some(timeout));
+            self.admin
+                .create_partitions(&new_partitions, &admin_options)
+                .wait()
+        })?;
+
+        match rx.first() {
+            None => Err(Error::Broker(format!(
+                "No result returned for topic `{}`",
+                topic_name
+            ))),
+            // Print topic name only if successful
+            Some(Ok(t)) => self.output.print(t),
+            Some(Err((n, RDKafkaError::InvalidPartitions))) if attempts > 1 => {
+                log::info!(
+                    "Partitions of topic `{}` were probably added by an earlier attempt.",
+                    n
+                );
+                self.output.print(n)
+            }
+            Some(Err((n, e))) => Err(Error::from_code(
+                format!(
+                    "Failed to add partitions to topic `{}`. Reason: `{:?}`",
+                    n, e
+                ),
+                *e,
+            )),
+        }
+    }
+}
+
+impl TryFrom<Config> for AddPartitionsCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `topics add-partitions`".into())
+        })?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
         })
     }
 }
 
 pub struct DeleteCommand {
-    admin: AdminClient<DefaultClientContext>,
+    admin: AdminClient<LoggingContext>,
+    // Only used to list topics when deleting by pattern.
+    consumer: BaseConsumer<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+// Asks the user for confirmation on stderr. Anything other than y/yes
+// (including EOF) counts as a no.
+fn confirm(prompt: &str) -> crate::Result<bool> {
+    eprint!("{} [y/N] ", prompt);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(["y", "yes"].contains(&answer.trim().to_lowercase().as_str()))
 }
 
 impl DeleteCommand {
     pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
         SubCommand::with_name("delete")
-            .about("Deletes the specified Kafka topic.")
-            .arg(args::topic().required(true))
+            .about("Deletes the specified Kafka topics.")
+            .arg(
+                args::topic()
+                    .help("Kafka topic name. Specify multiple times for multiple topics.")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required_unless("regex"),
+            )
+            .arg(args::regex().help("Also delete all topics whose full names match this regex. Internal topics (e.g. __consumer_offsets) are only deleted if specified with -t."))
+            .arg(args::yes())
     }
 
-    pub fn run(&self, topic_name: &str) -> crate::Result<()> {
-        let rx = self
-            .admin
-            .delete_topics(&[topic_name], &AdminOptions::new())
-            .wait()?;
+    pub fn run(
+        &self,
+        topic_names: &[&str],
+        pattern: Option<&Regex>,
+        yes: bool,
+    ) -> crate::Result<()> {
+        let mut matched: Vec<String> = topic_names.iter().map(|&t| t.to_owned()).collect();
+        if let Some(re) = pattern {
+            // Like `kafka-topics --delete`, the regex has to match the whole
+            // name, so that e.g. `test` doesn't also delete `my-test-topic`.
+            let re = Regex::new(&format!("^(?:{})$", re.as_str())).map_err(|e| {
+                Error::InvalidUsage(format!("Invalid regular expression for regex: {}", e))
+            })?;
+            let md = self.retry.run("fetch metadata", |timeout| {
+                self.consumer.fetch_metadata(None, Some(timeout))
+            })?;
+            matched.extend(
+                md.topics()
+                    .iter()
+                    .map(|t| t.name())
+                    .filter(|n| !is_internal_topic(n) && re.is_match(n))
+                    .map(|n| n.to_owned()),
+            );
+        }
+        matched.sort();
+        matched.dedup();
 
-        rx[0]
-            .as_ref()
-            // Print topic name only if successful
-            .map(|t| println!("{}", t))
-            .map_err(|(n, e)| {
-                Error::Generic(format!("Failed to delete topic `{}`. Reason: `{:?}`", n, e))
-            })
+        if matched.is_empty() {
+            log::info!("No topics matched, nothing to delete.");
+            return Ok(());
+        }
+
+        eprintln!("The following topics will be deleted:");
+        for t in matched.iter() {
+            eprintln!("  {}", t);
+        }
+        if !yes && !confirm(&format!("Delete {} topic(s)?", matched.len()))? {
+            return Err(Error::Generic("Aborted, no topics were deleted.".into()));
+        }
+
+        let names: Vec<&str> = matched.iter().map(String::as_str).collect();
+        // A request that timed out might still have deleted the topics.
+        let mut attempts = 0;
+        let rx = self.retry.run("delete topics", |timeout| {
+            attempts += 1;
+            let admin_options = AdminOptions::new().request_timeout(Some(timeout));
+            self.admin.delete_topics(&names, &admin_options).wait()
+        })?;
+
+        let mut deleted = Vec::new();
+        let mut first_error = None;
+        for r in rx.iter() {
+            match r {
+                Ok(t) => deleted.push(t),
+                Err((n, RDKafkaError::UnknownTopicOrPartition)) if attempts > 1 => deleted.push(n),
+                Err((n, e)) => {
+                    log::error!("Failed to delete topic `{}`. Reason: `{:?}`", n, e);
+                    first_error = first_error.or(Some(*e));
+                }
+            }
+        }
+        // Print only the names of successfully deleted topics
+        self.output.print(&deleted)?;
+
+        match first_error {
+            // The exit code follows the first failure.
+            Some(e) => Err(Error::from_code(
+                format!(
+                    "Failed to delete {} out of {} topic(s).",
+                    rx.len() - deleted.len(),
+                    rx.len()
+                ),
+                e,
+            )),
+            None => Ok(()),
+        }
     }
 }
 
@@ -257,10 +773,144 @@ impl TryFrom<Config> for DeleteCommand {
         let brokers = conf
             .brokers
             .as_ref()
-            .ok_or_else(|| Error::InvalidUsage("brokers is required for `create`".into()))?;
+            .ok_or_else(|| Error::InvalidUsage("brokers is required for `topics delete`".into()))?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            consumer: new_consumer(&conf, brokers, None)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
+
+pub struct ConfigGetCommand {
+    admin: AdminClient<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ConfigGetCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("get")
+            .about("Shows the configs of the specified Kafka topic.")
+            .arg(args::topic().required(true))
+    }
+
+    pub fn run(&self, topic_name: &str) -> crate::Result<()> {
+        let resource = describe_config(
+            &self.admin,
+            &self.retry,
+            ResourceSpecifier::Topic(topic_name),
+        )?;
+        let infos: Vec<ConfigEntryInfo> = resource.entries.iter().map(|e| e.into()).collect();
+
+        self.output.print(&infos)
+    }
+}
+
+impl TryFrom<Config> for ConfigGetCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `topics config get`".into())
+        })?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
+
+pub struct ConfigSetCommand {
+    admin: AdminClient<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ConfigSetCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("set")
+            .about("Sets configs of the specified Kafka topic.")
+            .arg(args::topic().required(true))
+            .arg(args::config_entries().required(true))
+    }
+
+    pub fn run(&self, topic_name: &str, entries: &[(String, String)]) -> crate::Result<()> {
+        alter_config(
+            &self.admin,
+            &self.retry,
+            ResourceSpecifier::Topic(topic_name),
+            &ConfigSource::DynamicTopic,
+            entries,
+            &[],
+        )?;
+
+        // Print topic name only if successful
+        self.output.print(&topic_name)
+    }
+}
+
+impl TryFrom<Config> for ConfigSetCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `topics config set`".into())
+        })?;
+
+        Ok(Self {
+            admin: new_admin_client(&conf, brokers)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
+        })
+    }
+}
+
+pub struct ConfigUnsetCommand {
+    admin: AdminClient<LoggingContext>,
+    retry: RetryPolicy,
+    output: OutputFormat,
+}
+
+impl ConfigUnsetCommand {
+    pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("unset")
+            .about("Reverts configs of the specified Kafka topic to their defaults.")
+            .arg(args::topic().required(true))
+            .arg(args::config_keys().required(true))
+    }
+
+    pub fn run(&self, topic_name: &str, keys: &[String]) -> crate::Result<()> {
+        alter_config(
+            &self.admin,
+            &self.retry,
+            ResourceSpecifier::Topic(topic_name),
+            &ConfigSource::DynamicTopic,
+            &[],
+            keys,
+        )?;
+
+        // Print topic name only if successful
+        self.output.print(&topic_name)
+    }
+}
+
+impl TryFrom<Config> for ConfigUnsetCommand {
+    type Error = Error;
+
+    fn try_from(conf: Config) -> crate::Result<Self> {
+        let brokers = conf.brokers.as_ref().ok_or_else(|| {
+            Error::InvalidUsage("brokers is required for `topics config unset`".into())
+        })?;
 
         Ok(Self {
-            admin: new_admin_client(&brokers),
+            admin: new_admin_client(&conf, brokers)?,
+            retry: conf.retry_policy()?,
+            output: conf.output_format(),
         })
     }
 }
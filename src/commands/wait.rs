@@ -1,42 +1,342 @@
 use std::convert::TryFrom;
+use std::time::{Duration, Instant};
 
 use clap::{App, SubCommand};
 use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::message::{Headers, Message, OwnedMessage};
+use rdkafka::metadata::Metadata;
+use rdkafka::{Offset, TopicPartitionList};
+use regex::Regex;
 
-use crate::{new_consumer, Config, Error, DEFAULT_TIMEOUT};
+use crate::args;
+use crate::commands::consumer::print_message;
+use crate::commands::topics::{is_internal_topic, is_offline};
+use crate::retry::{INITIAL_BACKOFF, MAX_BACKOFF};
+use crate::{
+    new_assigned_consumer, new_consumer, Config, Error, LoggingContext, OutputFormat,
+    DEFAULT_TIMEOUT,
+};
+
+/// Conditions that all have to be met before `wait` returns.
+#[derive(Debug)]
+pub struct WaitConditions {
+    pub min_brokers: usize,
+    // Topics that have to exist with leaders for all partitions.
+    pub topics: Vec<String>,
+    // Consumer groups that have to be in the Stable state.
+    pub groups: Vec<String>,
+    // Total lag of the consumer group (from `-g/--group-id`) over `topics`
+    // (or all topics it has committed offsets for) has to be below this.
+    pub lag_below: Option<i64>,
+    // Once everything else is met, a record matching this has to show up in
+    // `topics`.
+    pub message_matching: Option<MessageMatch>,
+}
+
+/// A record that `wait` looks for in its topics.
+#[derive(Debug)]
+pub struct MessageMatch {
+    // Matched against the key, the payload and each header value.
+    pub pattern: Regex,
+    // Where to start reading each partition from.
+    pub start: Offset,
+}
+
+impl MessageMatch {
+    fn is_match(&self, msg: &OwnedMessage) -> bool {
+        let matches = |bytes: &[u8]| self.pattern.is_match(&String::from_utf8_lossy(bytes));
+        if msg.key().into_iter().chain(msg.payload()).any(matches) {
+            return true;
+        }
+        match msg.headers() {
+            Some(headers) => (0..headers.count())
+                .filter_map(|i| headers.get(i))
+                .any(|(_, value)| matches(value)),
+            None => false,
+        }
+    }
+}
+
+// Parses where to start reading partitions from: `beginning`, `end` or an
+// offset.
+pub fn parse_start_offset(s: &str) -> crate::Result<Offset> {
+    match s {
+        "beginning" => Ok(Offset::Beginning),
+        "end" => Ok(Offset::End),
+        _ => s.parse::<i64>().map(Offset::Offset).map_err(|_| {
+            Error::InvalidUsage(format!(
+                "Expected start offset to be beginning, end or a number, but got `{}`",
+                s
+            ))
+        }),
+    }
+}
+
+impl Default for WaitConditions {
+    fn default() -> Self {
+        Self {
+            min_brokers: 1,
+            topics: Vec::new(),
+            groups: Vec::new(),
+            lag_below: None,
+            message_matching: None,
+        }
+    }
+}
 
 pub struct WaitCommand {
-    consumer: BaseConsumer,
+    consumer: BaseConsumer<LoggingContext>,
+    // Uses the group ID from the config, to look up committed offsets.
+    group_consumer: BaseConsumer<LoggingContext>,
+    // Reads the topics for `--message-matching`.
+    reader: BaseConsumer<LoggingContext>,
+    output: OutputFormat,
 }
 
 impl WaitCommand {
     pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name("wait").about("Waits until the specified Kafka broker is ready.")
+        SubCommand::with_name("wait")
+            .about("Waits until the Kafka cluster is ready.")
+            .long_about("Waits until the Kafka cluster is ready.\n\nBy default, waits until at least one broker is reachable. Additional conditions can be specified, in which case all of them have to be met.")
+            .arg(args::min_brokers())
+            .arg(
+                args::topic()
+                    .help("Wait until the topic exists and all its partitions have leaders. Specify multiple times for multiple topics.")
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(args::wait_group())
+            .arg(args::lag_below())
+            .arg(args::message_matching())
+            .arg(args::start_offset())
     }
 
-    pub fn run(&self) -> crate::Result<()> {
-        eprintln!("Waiting for Kafka broker to be ready (press Ctrl+C to interrupt).");
-        loop {
-            let md = match self.consumer.fetch_metadata(None, Some(DEFAULT_TIMEOUT)) {
-                Ok(v) => v,
-                Err(e) => {
-                    eprintln!("Error connecting to Kafka broker - either Kafka is not up or not ready: {:?}", e);
-                    continue;
+    // Timeout for a single request, which must not run past the deadline.
+    fn request_timeout(&self, deadline: Option<Instant>) -> Duration {
+        match deadline {
+            Some(deadline) => {
+                DEFAULT_TIMEOUT.min(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => DEFAULT_TIMEOUT,
+        }
+    }
+
+    // Returns None if all conditions are met, or the reason why not.
+    fn check(
+        &self,
+        conditions: &WaitConditions,
+        deadline: Option<Instant>,
+    ) -> crate::Result<Option<String>> {
+        let md = self
+            .consumer
+            .fetch_metadata(None, Some(self.request_timeout(deadline)))?;
+
+        if md.brokers().len() < conditions.min_brokers {
+            return Ok(Some(format!(
+                "Found {} broker(s), expected at least {}.",
+                md.brokers().len(),
+                conditions.min_brokers
+            )));
+        }
+
+        // Look for topics in the full metadata, since asking for a specific
+        // topic might auto-create it.
+        for name in conditions.topics.iter() {
+            match md.topics().iter().find(|t| t.name() == name) {
+                None => return Ok(Some(format!("Topic `{}` does not exist yet.", name))),
+                Some(t) if t.partitions().is_empty() => {
+                    return Ok(Some(format!("Topic `{}` has no partitions yet.", name)))
                 }
-            };
-            let topic_count = md
-                .topics()
-                .iter()
-                .filter(|&t| t.name() == "__confluent.support.metrics")
-                .count();
-
-            if topic_count == 1 {
-                break;
+                Some(t) if t.partitions().iter().any(|p| is_offline(p, md.brokers())) => {
+                    return Ok(Some(format!(
+                        "Some partitions of topic `{}` have no leader yet.",
+                        name
+                    )))
+                }
+                Some(_) => (),
+            }
+        }
+
+        for name in conditions.groups.iter() {
+            let groups = self
+                .consumer
+                .fetch_group_list(Some(name), Some(self.request_timeout(deadline)))?;
+            match groups.groups().iter().find(|g| g.name() == name) {
+                Some(g) if g.state() == "Stable" => (),
+                Some(g) => {
+                    return Ok(Some(format!(
+                        "Group `{}` is in state {}, expected Stable.",
+                        name,
+                        g.state()
+                    )))
+                }
+                None => return Ok(Some(format!("Group `{}` does not exist yet.", name))),
+            }
+        }
+
+        if let Some(max_lag) = conditions.lag_below {
+            match self.group_lag(&md, &conditions.topics, deadline)? {
+                None => return Ok(Some("Group has not committed any offsets yet.".into())),
+                Some(lag) if lag >= max_lag => {
+                    return Ok(Some(format!(
+                        "Group has lag {}, expected below {}.",
+                        lag, max_lag
+                    )))
+                }
+                Some(_) => (),
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Returns the total lag of the group over the given topics, or over all
+    // topics it has committed offsets for if none are given. Returns None if
+    // no topics are given and the group has not committed anything yet.
+    fn group_lag(
+        &self,
+        md: &Metadata,
+        topics: &[String],
+        deadline: Option<Instant>,
+    ) -> crate::Result<Option<i64>> {
+        let mut tpl = TopicPartitionList::new();
+        for t in md.topics() {
+            let selected = if topics.is_empty() {
+                !is_internal_topic(t.name())
             } else {
-                eprintln!("Did not find __confluent.support.metrics in the list of topics.");
+                topics.iter().any(|name| name == t.name())
+            };
+            if selected {
+                for p in t.partitions() {
+                    tpl.add_partition(t.name(), p.id());
+                }
+            }
+        }
+
+        let committed = self
+            .group_consumer
+            .committed_offsets(tpl, Some(self.request_timeout(deadline)))?;
+        let mut lag = 0;
+        let mut any_committed = false;
+        for elem in committed.elements() {
+            let committed_offset = match elem.offset() {
+                Offset::Offset(o) => Some(o),
+                _ => None,
+            };
+            if committed_offset.is_none() && topics.is_empty() {
+                // Not consumed by this group.
+                continue;
+            }
+
+            let (low, high) = self.consumer.fetch_watermarks(
+                elem.topic(),
+                elem.partition(),
+                Some(self.request_timeout(deadline)),
+            )?;
+            any_committed |= committed_offset.is_some();
+            lag += (high - committed_offset.unwrap_or(low)).max(0);
+        }
+
+        if any_committed || !topics.is_empty() {
+            Ok(Some(lag))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn wait_until_ready(
+        &self,
+        conditions: &WaitConditions,
+        deadline: Option<Instant>,
+    ) -> crate::Result<()> {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let reason = match self.check(conditions, deadline) {
+                Ok(None) => return Ok(()),
+                Ok(Some(reason)) => reason,
+                Err(e) => format!(
+                    "Error connecting to Kafka broker - either Kafka is not up or not ready: {}",
+                    e
+                ),
+            };
+            log::info!("{}", reason);
+
+            let mut sleep = backoff;
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(Error::Timeout(format!(
+                        "Timed out waiting for Kafka cluster to be ready. Last status: {}",
+                        reason
+                    )));
+                }
+                sleep = sleep.min(deadline - now);
+            }
+            std::thread::sleep(sleep);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    // Reads all partitions of the topics until a matching record shows up,
+    // and prints it.
+    fn wait_for_message(
+        &self,
+        topics: &[String],
+        message_match: &MessageMatch,
+        deadline: Option<Instant>,
+    ) -> crate::Result<()> {
+        let md = self
+            .consumer
+            .fetch_metadata(None, Some(self.request_timeout(deadline)))?;
+        let mut tpl = TopicPartitionList::new();
+        for t in md.topics() {
+            if topics.iter().any(|name| name == t.name()) {
+                for p in t.partitions() {
+                    tpl.add_partition_offset(t.name(), p.id(), message_match.start);
+                }
+            }
+        }
+        self.reader.assign(&tpl)?;
+
+        log::info!("Waiting for a record matching `{}`.", message_match.pattern);
+        while deadline.iter().all(|&d| Instant::now() < d) {
+            match self.reader.poll(Duration::from_millis(100)) {
+                Some(Ok(msg)) => {
+                    let msg = msg.detach();
+                    if message_match.is_match(&msg) {
+                        print_message(self.output, &msg);
+                        return Ok(());
+                    }
+                }
+                Some(Err(e)) => log::warn!("Error while receiving from Kafka: {}", e),
+                None => (),
+            }
+        }
+
+        Err(Error::Timeout(format!(
+            "Timed out waiting for a record matching `{}`.",
+            message_match.pattern
+        )))
+    }
+
+    pub fn run(&self, conditions: &WaitConditions, timeout: Option<Duration>) -> crate::Result<()> {
+        log::info!("Waiting for Kafka cluster to be ready (press Ctrl+C to interrupt).");
+        let now = Instant::now();
+        let deadline = match timeout {
+            Some(t) => Some(
+                now.checked_add(t)
+                    .ok_or_else(|| Error::InvalidUsage(format!("Timeout {:?} is too long", t)))?,
+            ),
+            None => None,
+        };
+        self.wait_until_ready(conditions, deadline)?;
+
+        match &conditions.message_matching {
+            Some(message_match) => {
+                self.wait_for_message(&conditions.topics, message_match, deadline)
             }
+            None => Ok(()),
         }
-        Ok(())
     }
 }
 
@@ -50,7 +350,18 @@ impl TryFrom<Config> for WaitCommand {
             .ok_or_else(|| Error::InvalidUsage("brokers is required for `wait`".into()))?;
 
         Ok(Self {
-            consumer: new_consumer(&brokers, None),
+            consumer: new_consumer(&conf, brokers, None)?,
+            group_consumer: new_consumer(
+                &conf,
+                brokers,
+                conf.group_id.as_ref().map(|g| g.value.as_str()),
+            )?,
+            reader: new_assigned_consumer(
+                &conf,
+                brokers,
+                conf.group_id.as_ref().map(|g| g.value.as_str()),
+            )?,
+            output: conf.output_format(),
         })
     }
 }
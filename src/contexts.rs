@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::security::{SaslSettings, TlsSettings};
+use crate::{Error, Result};
+
+pub const CONFIG_PATH_ENV_KEY: &str = "KRS_CONFIG";
+
+/// Named set of connection settings, e.g. for dev/staging/prod clusters.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Context {
+    pub brokers: Option<String>,
+    pub zookeeper: Option<String>,
+    pub group_id: Option<String>,
+    // E.g. `30s`, like `--timeout`.
+    pub timeout: Option<String>,
+    pub retries: Option<u32>,
+    pub tls: Option<TlsSettings>,
+    pub sasl: Option<SaslSettings>,
+    // librdkafka properties, like `-X`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rdkafka: BTreeMap<String, String>,
+}
+
+/// The user config file, `~/.config/krs/config.toml` by default:
+///
+/// ```toml
+/// current_context = "dev"
+///
+/// [contexts.dev]
+/// brokers = "localhost:9092"
+///
+/// [contexts.prod]
+/// brokers = "kafka-1.prod:9092"
+/// zookeeper = "zk-1.prod:2181"
+/// group_id = "my-debugging-group"
+/// timeout = "30s"
+/// retries = 3
+///
+/// [contexts.prod.tls]
+/// ca_file = "/etc/kafka/prod-ca.pem"
+///
+/// [contexts.prod.sasl]
+/// mechanism = "SCRAM-SHA-512"
+/// username = "me"
+/// password_cmd = "pass show kafka/prod"
+///
+/// [contexts.prod.rdkafka]
+/// "consumer.fetch.max.bytes" = "1048576"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserConfig {
+    pub current_context: Option<String>,
+    #[serde(default)]
+    pub contexts: BTreeMap<String, Context>,
+}
+
+impl UserConfig {
+    // $KRS_CONFIG, or the krs directory in $XDG_CONFIG_HOME (defaults to
+    // ~/.config).
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_PATH_ENV_KEY) {
+            return Some(PathBuf::from(path));
+        }
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("krs").join("config.toml"))
+    }
+
+    // A missing file is the same as an empty one.
+    pub fn load() -> Result<Self> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+        let contents = fs::read_to_string(&path)?;
+        toml::from_str(&contents)
+            .map_err(|e| Error::InvalidUsage(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    // Only rewrites the top-level `current_context` line (or adds one at the
+    // top), so that comments and the order of the contexts are kept.
+    pub fn save_current_context(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| {
+            Error::Generic(format!(
+                "Cannot find a place for the config file, set {} or HOME.",
+                CONFIG_PATH_ENV_KEY
+            ))
+        })?;
+        let contents = if path.exists() {
+            fs::read_to_string(&path)?
+        } else {
+            String::new()
+        };
+
+        let line = self
+            .current_context
+            .as_ref()
+            .map(|name| format!("current_context = {}", toml::Value::from(name.as_str())));
+        let mut lines: Vec<String> = Vec::new();
+        let mut in_table = false;
+        let mut found = false;
+        for l in contents.lines() {
+            let trimmed = l.trim_start();
+            in_table |= trimmed.starts_with('[');
+            let is_current_context = !in_table
+                && trimmed
+                    .split('=')
+                    .next()
+                    .map_or(false, |k| k.trim() == "current_context");
+            if !is_current_context {
+                lines.push(l.to_owned());
+            } else if !found {
+                lines.extend(line.clone());
+                found = true;
+            }
+        }
+        if !found {
+            // Top-level keys have to come before any table.
+            if let Some(line) = line {
+                lines.insert(0, line);
+            }
+        }
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        // Don't leave a file behind that can't be loaded anymore.
+        toml::from_str::<UserConfig>(&contents)
+            .map_err(|e| Error::Generic(format!("Failed to update {}: {}", path.display(), e)))?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // The file can contain credentials, so it's only readable by the
+        // user, and replaced in one go.
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".new");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        // `mode` only applies to new files.
+        fs::remove_file(&tmp_path).ok();
+        let mut f = options.open(&tmp_path)?;
+        f.write_all(contents.as_bytes())?;
+        f.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    pub fn context(&self, name: &str) -> Result<&Context> {
+        self.contexts.get(name).ok_or_else(|| {
+            Error::InvalidUsage(format!(
+                "Context `{}` not found in {}",
+                name,
+                Self::path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "the config file".into())
+            ))
+        })
+    }
+}
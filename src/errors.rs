@@ -1,23 +1,127 @@
-use std::fmt::{Debug, Display};
+use std::fmt::Display;
+
+use rdkafka::error::{KafkaError, RDKafkaError};
+
+/// Exit codes for each category of errors, so that scripts can tell them
+/// apart. `krs` exits with 0 on success.
+pub mod exit_code {
+    /// Any other error, e.g. `krs health` found problems in the cluster.
+    pub const FAILURE: i32 = 1;
+    /// Missing or invalid arguments or settings.
+    pub const USAGE: i32 = 2;
+    /// Authentication with the brokers failed, or the operation is not
+    /// authorized.
+    pub const AUTH: i32 = 3;
+    /// The topic, group or other resource does not exist.
+    pub const NOT_FOUND: i32 = 4;
+    /// The brokers didn't respond in time, see `--timeout`.
+    pub const TIMEOUT: i32 = 5;
+    /// The brokers returned an error, or couldn't be reached.
+    pub const BROKER: i32 = 6;
+}
 
 #[derive(Debug)]
 pub enum Error {
     InvalidUsage(String),
     Generic(String),
-    Kafka(rdkafka::error::KafkaError),
+    Auth(String),
+    NotFound(String),
+    Timeout(String),
+    // An error the brokers returned for a single resource, e.g. one of the
+    // topics in a request.
+    Broker(String),
+    Kafka(KafkaError),
     Clap(clap::Error),
     Io(std::io::Error),
     Other(Box<dyn std::error::Error>),
 }
 
+// The error code of Kafka errors that have one.
+fn kafka_error_code(e: &KafkaError) -> Option<RDKafkaError> {
+    match e {
+        KafkaError::AdminOp(code)
+        | KafkaError::ConsumerCommit(code)
+        | KafkaError::Global(code)
+        | KafkaError::GroupListFetch(code)
+        | KafkaError::MessageConsumption(code)
+        | KafkaError::MessageProduction(code)
+        | KafkaError::MetadataFetch(code)
+        | KafkaError::OffsetFetch(code)
+        | KafkaError::SetPartitionOffset(code)
+        | KafkaError::StoreOffset(code) => Some(*code),
+        _ => None,
+    }
+}
+
+fn exit_code_of(code: RDKafkaError) -> i32 {
+    match code {
+        RDKafkaError::Authentication
+        | RDKafkaError::SaslAuthenticationFailed
+        | RDKafkaError::TopicAuthorizationFailed
+        | RDKafkaError::GroupAuthorizationFailed
+        | RDKafkaError::ClusterAuthorizationFailed => exit_code::AUTH,
+        RDKafkaError::UnknownTopicOrPartition
+        | RDKafkaError::UnknownTopic
+        | RDKafkaError::UnknownPartition
+        | RDKafkaError::UnknownGroup
+        | RDKafkaError::GroupIdNotFound => exit_code::NOT_FOUND,
+        RDKafkaError::OperationTimedOut
+        | RDKafkaError::RequestTimedOut
+        | RDKafkaError::MessageTimedOut
+        | RDKafkaError::TimedOutQueue => exit_code::TIMEOUT,
+        _ => exit_code::BROKER,
+    }
+}
+
+impl Error {
+    // Picks the category from the error code the brokers returned for a
+    // single resource.
+    pub(crate) fn from_code(message: String, code: RDKafkaError) -> Self {
+        match exit_code_of(code) {
+            exit_code::AUTH => Error::Auth(message),
+            exit_code::NOT_FOUND => Error::NotFound(message),
+            exit_code::TIMEOUT => Error::Timeout(message),
+            _ => Error::Broker(message),
+        }
+    }
+
+    /// The exit code for this error, see [`exit_code`](exit_code/index.html).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidUsage(_) | Error::Clap(_) => exit_code::USAGE,
+            Error::Auth(_) => exit_code::AUTH,
+            Error::NotFound(_) => exit_code::NOT_FOUND,
+            Error::Timeout(_) => exit_code::TIMEOUT,
+            Error::Broker(_) => exit_code::BROKER,
+            // Rejected by librdkafka, e.g. an unknown `-X` property.
+            Error::Kafka(KafkaError::ClientConfig(..)) => exit_code::USAGE,
+            Error::Kafka(e) => kafka_error_code(e)
+                .map(exit_code_of)
+                .unwrap_or(exit_code::FAILURE),
+            Error::Generic(_) | Error::Io(_) | Error::Other(_) => exit_code::FAILURE,
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        Debug::fmt(self, f)
+        match self {
+            Error::InvalidUsage(msg)
+            | Error::Generic(msg)
+            | Error::Auth(msg)
+            | Error::NotFound(msg)
+            | Error::Timeout(msg)
+            | Error::Broker(msg) => f.write_str(msg),
+            Error::Kafka(e) => write!(f, "Kafka error: {}", e),
+            Error::Clap(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Other(e) => write!(f, "{}", e),
+        }
     }
 }
 
-impl From<rdkafka::error::KafkaError> for Error {
-    fn from(e: rdkafka::error::KafkaError) -> Self {
+impl From<KafkaError> for Error {
+    fn from(e: KafkaError) -> Self {
         Error::Kafka(e)
     }
 }
@@ -40,4 +144,28 @@ impl From<clap::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Other(Box::new(e))
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::Other(Box::new(e))
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Other(Box::new(e))
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Error::Other(Box::new(e))
+    }
+}
+
 impl std::error::Error for Error {}
@@ -1,31 +1,47 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fmt::{Debug, Display};
 use std::time::Duration;
 
-use chrono::offset::Utc;
 use clap::{crate_authors, crate_version, App, ArgMatches, SubCommand};
-use dotenv;
 use rdkafka::admin::AdminClient;
-use rdkafka::client::DefaultClientContext;
-use rdkafka::config::FromClientConfig;
-use rdkafka::consumer::Consumer;
+use rdkafka::client::Client;
+use rdkafka::config::FromClientConfigAndContext;
+use rdkafka::consumer::{BaseConsumer, Consumer};
 use rdkafka::producer::FutureProducer;
+use rdkafka::types::RDKafkaType;
 use rdkafka::ClientConfig;
+use regex::Regex;
+use zookeeper::{WatchedEvent, Watcher, ZooKeeper};
 
 mod args;
 pub mod commands;
+pub mod contexts;
 pub mod errors;
+pub mod logging;
+pub mod output;
+pub mod retry;
+pub mod security;
 
+pub use contexts::{Context, UserConfig};
 pub use errors::Error;
+pub use logging::{LoggingContext, Verbosity};
+pub use output::OutputFormat;
+pub use retry::RetryPolicy;
+pub use security::{SaslConfig, TlsConfig};
 
-// TODO: Move to global CLI arg as well.
+// Defaults for `--timeout` and `--retries`.
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_RETRIES: u32 = 0;
 
 pub const VERSION: &str = env!("GIT_DESCRIPTION");
 
 pub const BROKERS_ENV_KEY: &str = "KRS_BROKERS";
 pub const ZOOKEEPER_ENV_KEY: &str = "KRS_ZOOKEEPER";
+pub const GROUP_ID_ENV_KEY: &str = "KRS_GROUP_ID";
+pub const TIMEOUT_ENV_KEY: &str = "KRS_TIMEOUT";
+pub const RETRIES_ENV_KEY: &str = "KRS_RETRIES";
+pub const RDKAFKA_ENV_PREFIX: &str = "KRS_RDKAFKA_";
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -65,53 +81,178 @@ impl<T: Display> Display for Sourced<T> {
 #[derive(Debug, Default)]
 pub struct Config {
     pub brokers: Option<Sourced<String>>,
-    pub group_id: Option<String>,
+    pub group_id: Option<Sourced<String>>,
 
     pub zookeeper: Option<Sourced<String>>,
+
+    pub output: Option<OutputFormat>,
+
+    // Kept as given (e.g. `30s`), and only parsed by the commands that use
+    // them, so that `krs env` still works with invalid values.
+    pub timeout: Option<Sourced<String>>,
+    pub retries: Option<Sourced<String>>,
+
+    // Name of the context from the user config file that is in use, if any.
+    pub context: Option<Sourced<String>>,
+
+    pub tls: TlsConfig,
+    pub sasl: SaslConfig,
+
+    // Passed as-is to librdkafka, e.g. `fetch.max.bytes`. Properties prefixed
+    // with `consumer.`, `producer.` or `admin.` only apply to that type of
+    // client.
+    pub rdkafka_properties: BTreeMap<String, Sourced<String>>,
 }
 
 impl Config {
-    pub fn init(args: &ArgMatches<'_>) -> Self {
-        Config::from_env()
-            .merge(Config::from_dotenv())
-            .merge(Config::from(args))
+    // The current context from the user config file has the lowest
+    // precedence, but a context selected with `--context` overrides env vars
+    // and the .env file. CLI arguments always win.
+    pub fn init(args: &ArgMatches<'_>) -> Result<Self> {
+        let user_config = UserConfig::load()?;
+        let (current, selected) = match args.value_of("context") {
+            Some(name) => (
+                Config::default(),
+                Config::from_context(&user_config, name, "--context")?,
+            ),
+            None => match &user_config.current_context {
+                // Don't fail here, so that `krs env use` can fix it.
+                Some(name) if !user_config.contexts.contains_key(name) => {
+                    log::warn!("Current context `{}` not found, ignoring it.", name);
+                    (Config::default(), Config::default())
+                }
+                Some(name) => (
+                    Config::from_context(&user_config, name, "current context")?,
+                    Config::default(),
+                ),
+                None => (Config::default(), Config::default()),
+            },
+        };
+
+        let config = current
+            .merge(Config::from_env()?)
+            .merge(Config::from_dotenv()?)
+            .merge(selected)
+            .merge(Config::from(args));
+        Ok(config)
     }
 
-    fn from_env() -> Self {
-        Self {
-            brokers: std::env::var(BROKERS_ENV_KEY)
-                .ok()
-                .map(|value| Sourced::new(&format!("env var ({})", BROKERS_ENV_KEY), value)),
-            zookeeper: std::env::var(ZOOKEEPER_ENV_KEY)
-                .ok()
-                .map(|value| Sourced::new(&format!(".env file ({})", ZOOKEEPER_ENV_KEY), value)),
+    // The timeout from `--timeout`, `KRS_TIMEOUT` or a context, if any.
+    pub fn timeout(&self) -> Result<Option<Duration>> {
+        self.timeout
+            .as_ref()
+            .map(|t| parse_duration(&t.value))
+            .transpose()
+    }
+
+    pub fn retry_policy(&self) -> Result<RetryPolicy> {
+        let retries = match &self.retries {
+            Some(r) => r.value.parse::<u32>().map_err(|_| {
+                Error::InvalidUsage(format!(
+                    "Expected retries to be a non-negative number, but got `{}` from {}",
+                    r.value, r.source
+                ))
+            })?,
+            None => DEFAULT_RETRIES,
+        };
+        Ok(RetryPolicy {
+            timeout: self.timeout()?.unwrap_or(DEFAULT_TIMEOUT),
+            retries,
+        })
+    }
+
+    // Falls back to detecting the output format from stdout if not specified.
+    pub fn output_format(&self) -> OutputFormat {
+        self.output.unwrap_or_else(OutputFormat::detect)
+    }
+
+    // Reads env var-like keys (e.g. `KRS_BROKERS`), recording `source_type`
+    // and the key as the source.
+    fn from_vars(vars: &HashMap<String, String>, source_type: &str) -> Result<Self> {
+        let source = |key: &str| format!("{} ({})", source_type, key);
+        let sourced = |key: &str| {
+            vars.get(key)
+                .map(|value| Sourced::new(&source(key), value.to_owned()))
+        };
+        Ok(Self {
+            brokers: sourced(BROKERS_ENV_KEY),
+            zookeeper: sourced(ZOOKEEPER_ENV_KEY),
+            group_id: sourced(GROUP_ID_ENV_KEY),
+            timeout: sourced(TIMEOUT_ENV_KEY),
+            retries: sourced(RETRIES_ENV_KEY),
+            tls: TlsConfig::from_vars(sourced),
+            sasl: SaslConfig::from_vars(sourced),
+            rdkafka_properties: vars
+                .iter()
+                .filter_map(|(key, value)| {
+                    property_from_env_key(key)
+                        .map(|property| (property, Sourced::new(&source(key), value.to_owned())))
+                })
+                .collect(),
             ..Default::default()
-        }
+        })
+    }
+
+    fn from_env() -> Result<Self> {
+        // Skip env vars that aren't valid unicode instead of panicking.
+        let vars = std::env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .collect();
+        Self::from_vars(&vars, "env var")
+    }
+
+    fn from_context(user_config: &UserConfig, name: &str, selected_by: &str) -> Result<Self> {
+        let context = user_config.context(name)?;
+        let source = format!("context {}", name);
+        Ok(Self {
+            brokers: context
+                .brokers
+                .as_ref()
+                .map(|value| Sourced::new(&source, value.to_owned())),
+            zookeeper: context
+                .zookeeper
+                .as_ref()
+                .map(|value| Sourced::new(&source, value.to_owned())),
+            group_id: context
+                .group_id
+                .as_ref()
+                .map(|value| Sourced::new(&source, value.to_owned())),
+            timeout: context
+                .timeout
+                .as_ref()
+                .map(|value| Sourced::new(&source, value.to_owned())),
+            retries: context
+                .retries
+                .map(|value| Sourced::new(&source, value.to_string())),
+            context: Some(Sourced::new(selected_by, name.to_owned())),
+            tls: context
+                .tls
+                .as_ref()
+                .map(|tls| TlsConfig::from_settings(tls, &source))
+                .unwrap_or_default(),
+            sasl: context
+                .sasl
+                .as_ref()
+                .map(|sasl| SaslConfig::from_settings(sasl, &source))
+                .unwrap_or_default(),
+            rdkafka_properties: context
+                .rdkafka
+                .iter()
+                .map(|(key, value)| (key.to_owned(), Sourced::new(&source, value.to_owned())))
+                .collect(),
+            ..Default::default()
+        })
     }
 
-    fn from_dotenv() -> Self {
+    fn from_dotenv() -> Result<Self> {
         // Will be undeprecated at some point.
         // https://github.com/dotenv-rs/dotenv/issues/13
         #[allow(deprecated)]
         let vars: HashMap<String, String> = dotenv::from_path_iter("./.env")
-            .map(|itr| itr.map(|x| x.ok()).flatten().collect())
+            .map(|itr| itr.filter_map(|x| x.ok()).collect())
             .unwrap_or_default();
 
-        Self {
-            brokers: vars.get(BROKERS_ENV_KEY).map(|value| {
-                Sourced::new(
-                    &format!(".env file ({})", BROKERS_ENV_KEY),
-                    value.to_owned(),
-                )
-            }),
-            zookeeper: vars.get(ZOOKEEPER_ENV_KEY).map(|value| {
-                Sourced::new(
-                    &format!(".env file ({})", ZOOKEEPER_ENV_KEY),
-                    value.to_owned(),
-                )
-            }),
-            ..Default::default()
-        }
+        Self::from_vars(&vars, ".env file")
     }
 
     // Merge two configs together, giving preference to `rhs` only if
@@ -121,60 +262,220 @@ impl Config {
             brokers: rhs.brokers.or(self.brokers),
             zookeeper: rhs.zookeeper.or(self.zookeeper),
             group_id: rhs.group_id.or(self.group_id),
+            output: rhs.output.or(self.output),
+            timeout: rhs.timeout.or(self.timeout),
+            retries: rhs.retries.or(self.retries),
+            context: rhs.context.or(self.context),
+            tls: self.tls.merge(rhs.tls),
+            sasl: self.sasl.merge(rhs.sasl),
+            rdkafka_properties: {
+                let mut properties = self.rdkafka_properties;
+                properties.extend(rhs.rdkafka_properties);
+                properties
+            },
         }
     }
 }
 
 impl From<&ArgMatches<'_>> for Config {
     fn from(args: &ArgMatches<'_>) -> Self {
-        let default_group_id = format!("krs-{}", Utc::now().timestamp_millis());
         Self {
+            // Both `-b a -b b` and `-b a,b` work.
             brokers: args
-                .value_of("brokers")
-                .map(|value| Sourced::new("-b/--brokers", value.to_owned())),
+                .values_of("brokers")
+                .map(|values| Sourced::new("-b/--brokers", values.collect::<Vec<_>>().join(","))),
             zookeeper: args
                 .value_of("zookeeper")
                 .map(|value| Sourced::new("-z/--zookeeper", value.to_owned())),
             group_id: args
                 .value_of("group-id")
-                .map(|x| x.to_owned())
-                .or(Some(default_group_id)),
+                .map(|value| Sourced::new("-g/--group-id", value.to_owned())),
+            // Already validated by clap.
+            output: args.value_of("output").and_then(|x| x.parse().ok()),
+            timeout: args
+                .value_of("timeout")
+                .map(|value| Sourced::new("--timeout", value.to_owned())),
+            retries: args
+                .value_of("retries")
+                .map(|value| Sourced::new("--retries", value.to_owned())),
+            // Only recorded by `Config::init`, which looks up the context.
+            context: None,
+            tls: TlsConfig::from(args),
+            sasl: SaslConfig::from(args),
+            // `--debug` is a shorthand for `-X debug=...`, which wins if both
+            // are given.
+            rdkafka_properties: args
+                .values_of("debug")
+                .map(|contexts| {
+                    let contexts = contexts.collect::<Vec<_>>().join(",");
+                    ("debug".to_owned(), Sourced::new("--debug", contexts))
+                })
+                .into_iter()
+                .chain(
+                    args.values_of("rdkafka_property")
+                        .into_iter()
+                        .flatten()
+                        // Already validated by clap.
+                        .filter_map(|v| parse_key_value(v).ok())
+                        .map(|(key, value)| (key, Sourced::new("-X", value))),
+                )
+                .collect(),
         }
     }
 }
 
-/// Creates a new Kafka consumer with only the parameters I care about.
-fn new_consumer<T>(brokers: &str, group_id: Option<&str>) -> T
+// Maps env vars like `KRS_RDKAFKA_FETCH_MAX_BYTES` to librdkafka properties
+// like `fetch.max.bytes`. A double underscore stands for an underscore.
+fn property_from_env_key(key: &str) -> Option<String> {
+    if !key.starts_with(RDKAFKA_ENV_PREFIX) || key.len() == RDKAFKA_ENV_PREFIX.len() {
+        return None;
+    }
+    Some(
+        key[RDKAFKA_ENV_PREFIX.len()..]
+            .to_lowercase()
+            .split("__")
+            .map(|part| part.replace('_', "."))
+            .collect::<Vec<_>>()
+            .join("_"),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClientType {
+    Consumer,
+    Producer,
+    Admin,
+}
+
+impl ClientType {
+    // Prefix of the properties that only apply to this type of client.
+    fn property_prefix(self) -> &'static str {
+        match self {
+            ClientType::Consumer => "consumer.",
+            ClientType::Producer => "producer.",
+            ClientType::Admin => "admin.",
+        }
+    }
+}
+
+// Applies the properties from `-X`, `KRS_RDKAFKA_*` and contexts last, so
+// that they override anything krs sets by itself.
+fn create_client<T>(conf: &Config, client_type: ClientType, mut config: ClientConfig) -> Result<T>
 where
-    T: Consumer + FromClientConfig,
+    T: FromClientConfigAndContext<LoggingContext>,
 {
+    apply_properties(conf, client_type, &mut config);
+    Ok(config.create_with_context(LoggingContext)?)
+}
+
+fn apply_properties(conf: &Config, client_type: ClientType, config: &mut ClientConfig) {
+    let scopes = [
+        ClientType::Consumer,
+        ClientType::Producer,
+        ClientType::Admin,
+    ];
+    for (key, value) in conf.rdkafka_properties.iter() {
+        match scopes.iter().find(|t| key.starts_with(t.property_prefix())) {
+            None => config.set(key, &value.value),
+            Some(&t) if t == client_type => {
+                config.set(&key[t.property_prefix().len()..], &value.value)
+            }
+            Some(_) => continue,
+        };
+    }
+}
+
+// Config shared by all Kafka clients, i.e. brokers and how to connect to them.
+fn client_config(conf: &Config, brokers: &str) -> Result<ClientConfig> {
+    // Brokers from the CLI are already validated by clap, but not the ones
+    // from other sources. They are only validated here, so that e.g. `env
+    // use` still works with broken brokers in the current context.
+    let source = conf
+        .brokers
+        .as_ref()
+        .map_or("brokers", |b| b.source.as_str());
+    let brokers = normalize_brokers(brokers, source)?;
     let mut config = ClientConfig::new();
-    config.set("bootstrap.servers", brokers);
+    config.set("bootstrap.servers", &brokers).set(
+        "security.protocol",
+        security::security_protocol(&conf.tls, &conf.sasl)?,
+    );
+    conf.tls.apply(&mut config)?;
+    conf.sasl.apply(&mut config)?;
+    Ok(config)
+}
+
+/// Creates a new Kafka consumer with only the parameters I care about.
+fn new_consumer<T>(conf: &Config, brokers: &str, group_id: Option<&str>) -> Result<T>
+where
+    T: Consumer<LoggingContext> + FromClientConfigAndContext<LoggingContext>,
+{
+    let mut config = client_config(conf, brokers)?;
 
     if let Some(v) = group_id {
         config.set("group.id", v);
     }
 
-    eprintln!(
+    log::debug!(
         "Created Consumer(brokers={}, group_id={:?})",
-        brokers, group_id
+        brokers,
+        group_id
     );
 
-    config.create().unwrap()
+    create_client(conf, ClientType::Consumer, config)
+}
+
+/// Creates a consumer for reading partitions with `assign()`, e.g. to peek at
+/// records. librdkafka only delivers records to consumers with a group ID, but
+/// since offsets are never committed, the group does not show up in the
+/// cluster.
+fn new_assigned_consumer(
+    conf: &Config,
+    brokers: &str,
+    group_id: Option<&str>,
+) -> Result<BaseConsumer<LoggingContext>> {
+    let mut config = client_config(conf, brokers)?;
+    config
+        .set("group.id", group_id.unwrap_or("krs"))
+        .set("enable.auto.commit", "false")
+        .set("enable.auto.offset.store", "false");
+
+    create_client(conf, ClientType::Consumer, config)
 }
 
-fn new_producer(brokers: &str) -> FutureProducer {
-    ClientConfig::new()
-        .set("bootstrap.servers", brokers)
-        .create()
-        .unwrap()
+/// Creates a plain client for what consumers and admin clients don't expose,
+/// e.g. the cluster ID and the controller. It is a producer underneath, since
+/// that doesn't join a group.
+fn new_client(conf: &Config, brokers: &str) -> Result<Client<LoggingContext>> {
+    let mut config = client_config(conf, brokers)?;
+    apply_properties(conf, ClientType::Producer, &mut config);
+    Ok(Client::new(
+        &config,
+        config.create_native_config()?,
+        RDKafkaType::RD_KAFKA_PRODUCER,
+        LoggingContext,
+    )?)
 }
 
-fn new_admin_client(brokers: &str) -> AdminClient<DefaultClientContext> {
-    ClientConfig::new()
-        .set("bootstrap.servers", brokers)
-        .create()
-        .unwrap()
+fn new_producer(conf: &Config, brokers: &str) -> Result<FutureProducer<LoggingContext>> {
+    create_client(conf, ClientType::Producer, client_config(conf, brokers)?)
+}
+
+fn new_admin_client(conf: &Config, brokers: &str) -> Result<AdminClient<LoggingContext>> {
+    create_client(conf, ClientType::Admin, client_config(conf, brokers)?)
+}
+
+struct DoNothingWatcher;
+impl Watcher for DoNothingWatcher {
+    fn handle(&self, _e: WatchedEvent) {
+        // Do nothing
+    }
+}
+
+fn new_zookeeper(zookeeper: &str, retry: &RetryPolicy) -> zookeeper::ZkResult<ZooKeeper> {
+    retry.run("connect to Zookeeper", |timeout| {
+        ZooKeeper::connect(zookeeper, timeout, DoNothingWatcher)
+    })
 }
 
 fn required<'a>(m: &'a ArgMatches<'a>, x: &str) -> Result<&'a str> {
@@ -188,6 +489,141 @@ fn required_i32(m: &ArgMatches<'_>, x: &str) -> Result<i32> {
         .map_err(|e| Error::InvalidUsage(format!("Expected integer argument for {}, but {}", x, e)))
 }
 
+fn required_values<'a>(m: &'a ArgMatches<'a>, x: &str) -> Result<Vec<String>> {
+    m.values_of(x)
+        .map(|v| v.map(|x| x.to_owned()).collect())
+        .ok_or_else(|| Error::InvalidUsage(format!("Argument is required for {}", x)))
+}
+
+fn optional_values(m: &ArgMatches<'_>, x: &str) -> Vec<String> {
+    m.values_of(x)
+        .map(|v| v.map(|x| x.to_owned()).collect())
+        .unwrap_or_default()
+}
+
+// Checks that a broker address looks like `host:port`, optionally with a
+// protocol like `SSL://host:port`. IPv6 addresses need brackets, e.g.
+// `[::1]:9092`.
+fn check_broker_address(address: &str) -> std::result::Result<(), String> {
+    let without_protocol = match address.find("://") {
+        Some(i) => &address[i + 3..],
+        None => address,
+    };
+    let (host, port) = match without_protocol.rfind(':') {
+        Some(i) => (&without_protocol[..i], &without_protocol[i + 1..]),
+        None => {
+            return Err(format!(
+                "Expected broker address like host:port, but got `{}`",
+                address
+            ))
+        }
+    };
+    if host.is_empty() || host.contains('/') || (host.contains(':') && !host.starts_with('[')) {
+        return Err(format!("Invalid host in broker address `{}`", address));
+    }
+    match port.parse::<u16>() {
+        Ok(p) if p > 0 => Ok(()),
+        _ => Err(format!("Invalid port in broker address `{}`", address)),
+    }
+}
+
+// Validates a comma-separated list of brokers, and removes whitespace and
+// empty entries from it.
+fn normalize_brokers(brokers: &str, source: &str) -> Result<String> {
+    let addresses: Vec<&str> = brokers
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .collect();
+    if addresses.is_empty() {
+        return Err(Error::InvalidUsage(format!(
+            "No brokers given in {}",
+            source
+        )));
+    }
+    for address in addresses.iter() {
+        check_broker_address(address)
+            .map_err(|e| Error::InvalidUsage(format!("{} (from {})", e, source)))?;
+    }
+    Ok(addresses.join(","))
+}
+
+// Parses durations like `500ms`, `30s`, `5m` or `1h`. Plain numbers are
+// treated as seconds.
+fn parse_duration(s: &str) -> Result<Duration> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let number = number.parse::<u64>().map_err(|e| {
+        Error::InvalidUsage(format!(
+            "Expected duration like 30s, but got `{}`: {}",
+            s, e
+        ))
+    })?;
+    let secs = |factor: u64| {
+        number
+            .checked_mul(factor)
+            .map(Duration::from_secs)
+            .ok_or_else(|| Error::InvalidUsage(format!("Duration `{}` is too long", s)))
+    };
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => secs(1),
+        "m" => secs(60),
+        "h" => secs(60 * 60),
+        _ => Err(Error::InvalidUsage(format!(
+            "Expected duration unit to be one of ms|s|m|h, but got `{}`",
+            s
+        ))),
+    }
+}
+
+// Parses `key=value` pairs, e.g. for topic configs.
+fn parse_key_value(s: &str) -> Result<(String, String)> {
+    let mut parts = s.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(k), Some(v)) if !k.is_empty() => Ok((k.to_owned(), v.to_owned())),
+        _ => Err(Error::InvalidUsage(format!(
+            "Expected argument in the form of key=value, but got `{}`",
+            s
+        ))),
+    }
+}
+
+fn required_key_values<'a>(m: &'a ArgMatches<'a>, x: &str) -> Result<Vec<(String, String)>> {
+    required_values(m, x)?
+        .iter()
+        .map(|s| parse_key_value(s))
+        .collect()
+}
+
+fn optional_key_values<'a>(m: &'a ArgMatches<'a>, x: &str) -> Result<Vec<(String, String)>> {
+    if m.is_present(x) {
+        required_key_values(m, x)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn optional_regex(m: &ArgMatches<'_>, x: &str) -> Result<Option<Regex>> {
+    m.value_of(x)
+        .map(|v| {
+            Regex::new(v).map_err(|e| {
+                Error::InvalidUsage(format!("Invalid regular expression for {}: {}", x, e))
+            })
+        })
+        .transpose()
+}
+
+fn broker_selection(m: &ArgMatches<'_>) -> Result<commands::brokers::BrokerSelection> {
+    if m.is_present("all") {
+        Ok(commands::brokers::BrokerSelection::All)
+    } else {
+        required_i32(m, "broker").map(commands::brokers::BrokerSelection::One)
+    }
+}
+
 // TODO: This function still looks really ugly. I wonder if I could macro this.
 pub fn dispatch(m: ArgMatches<'_>) -> Result<()> {
     fn fail(base: &str, subcmd: &str) -> Result<()> {
@@ -202,14 +638,33 @@ pub fn dispatch(m: ArgMatches<'_>) -> Result<()> {
             )
         };
 
-        Err(Error::Generic(msg))
+        Err(Error::InvalidUsage(msg))
+    }
+
+    // Doesn't need any settings, so that it works even with a broken config
+    // file.
+    if let ("completions", Some(s)) = m.subcommand() {
+        let shell = required(s, "shell")?.parse().map_err(Error::InvalidUsage)?;
+        return commands::completions::CompletionsCommand.run(shell);
     }
 
-    let config = Config::init(&m);
+    let config = Config::init(&m)?;
     // FIXME: Commands should implement TryFrom(config), not From.
     match m.subcommand() {
         ("topics", Some(s)) => match s.subcommand() {
-            ("list", _) => commands::topics::ListCommand::try_from(config)?.run(),
+            ("list", Some(ss)) => {
+                let options = commands::topics::ListOptions {
+                    pattern: optional_regex(ss, "regex")?,
+                    exclude_internal: ss.is_present("exclude_internal"),
+                    min_partitions: ss
+                        .value_of("min_partitions")
+                        .map(|v| v.parse::<usize>())
+                        .transpose()?,
+                    sort: ss.value_of("sort").map(|v| v.parse()).transpose()?,
+                    with_counts: ss.is_present("with_counts"),
+                };
+                commands::topics::ListCommand::try_from(config)?.run(&options)
+            }
             ("describe", Some(ss)) => {
                 let topic_name = required(ss, "topic")?;
                 commands::topics::DescribeCommand::try_from(config)?.run(topic_name)
@@ -218,22 +673,108 @@ pub fn dispatch(m: ArgMatches<'_>) -> Result<()> {
                 let topic_name = required(ss, "topic")?;
                 let num_partitions = required_i32(ss, "num_partitions")?;
                 let num_replicas = required_i32(ss, "num_replicas")?;
+                let options = commands::topics::CreateOptions {
+                    configs: optional_key_values(ss, "config")?,
+                    replica_assignment: ss
+                        .value_of("replica_assignment")
+                        .map(commands::topics::parse_replica_assignment)
+                        .transpose()?,
+                    if_not_exists: ss.is_present("if_not_exists"),
+                    validate_only: ss.is_present("validate_only"),
+                };
                 commands::topics::CreateCommand::try_from(config)?.run(
                     topic_name,
                     num_partitions,
                     num_replicas,
+                    &options,
                 )
             }
-            ("delete", Some(ss)) => {
+            ("add-partitions", Some(ss)) => {
                 let topic_name = required(ss, "topic")?;
-                commands::topics::DeleteCommand::try_from(config)?.run(topic_name)
+                let total = required_i32(ss, "total")?;
+                if total <= 0 {
+                    return Err(Error::InvalidUsage(
+                        "Expected positive integer argument for total".into(),
+                    ));
+                }
+                let assignment = ss
+                    .value_of("replica_assignment")
+                    .map(commands::topics::parse_replica_assignment)
+                    .transpose()?;
+                commands::topics::AddPartitionsCommand::try_from(config)?.run(
+                    topic_name,
+                    total as usize,
+                    assignment.as_ref().map(Vec::as_slice),
+                )
+            }
+            ("delete", Some(ss)) => {
+                let topic_names: Vec<&str> = ss.values_of("topic").into_iter().flatten().collect();
+                let pattern = optional_regex(ss, "regex")?;
+                commands::topics::DeleteCommand::try_from(config)?.run(
+                    &topic_names,
+                    pattern.as_ref(),
+                    ss.is_present("yes"),
+                )
             }
+            ("config", Some(ss)) => match ss.subcommand() {
+                ("get", Some(sss)) => {
+                    let topic_name = required(sss, "topic")?;
+                    commands::topics::ConfigGetCommand::try_from(config)?.run(topic_name)
+                }
+                ("set", Some(sss)) => {
+                    let topic_name = required(sss, "topic")?;
+                    let entries = required_key_values(sss, "entries")?;
+                    commands::topics::ConfigSetCommand::try_from(config)?.run(topic_name, &entries)
+                }
+                ("unset", Some(sss)) => {
+                    let topic_name = required(sss, "topic")?;
+                    let keys = required_values(sss, "keys")?;
+                    commands::topics::ConfigUnsetCommand::try_from(config)?.run(topic_name, &keys)
+                }
+                (unhandled, _) => fail("topics config", unhandled),
+            },
             // `krs topics` defaults to `krs topics show`
-            (_, _) => commands::topics::ListCommand::try_from(config)?.run(),
+            (_, _) => commands::topics::ListCommand::try_from(config)?.run(&Default::default()),
+        },
+        ("brokers", Some(s)) => match s.subcommand() {
+            ("list", _) => commands::brokers::ListCommand::try_from(config)?.run(),
+            ("config", Some(ss)) => match ss.subcommand() {
+                ("get", Some(sss)) => {
+                    let broker_id = required_i32(sss, "broker")?;
+                    commands::brokers::ConfigGetCommand::try_from(config)?.run(broker_id)
+                }
+                ("set", Some(sss)) => {
+                    let selection = broker_selection(sss)?;
+                    let entries = required_key_values(sss, "entries")?;
+                    commands::brokers::ConfigSetCommand::try_from(config)?.run(selection, &entries)
+                }
+                ("unset", Some(sss)) => {
+                    let selection = broker_selection(sss)?;
+                    let keys = required_values(sss, "keys")?;
+                    commands::brokers::ConfigUnsetCommand::try_from(config)?.run(selection, &keys)
+                }
+                (unhandled, _) => fail("brokers config", unhandled),
+            },
+            // `krs brokers` defaults to `krs brokers list`
+            (_, _) => commands::brokers::ListCommand::try_from(config)?.run(),
+        },
+        ("cluster", Some(s)) => match s.subcommand() {
+            ("describe", _) => commands::cluster::DescribeCommand::try_from(config)?.run(),
+            // `krs cluster` defaults to `krs cluster describe`
+            (_, _) => commands::cluster::DescribeCommand::try_from(config)?.run(),
         },
         ("env", Some(s)) => match s.subcommand() {
             ("show", _) => commands::env::ShowCommand::try_from(config)?.run(),
-            ("set", _) => commands::env::SetCommand::try_from(config)?.run(),
+            // Only writes what is given on the command line.
+            ("set", _) => commands::env::SetCommand::try_from(Config::from(&m))?.run(),
+            ("unset", Some(ss)) => {
+                let keys = required_values(ss, "keys")?;
+                commands::env::UnsetCommand::try_from(config)?.run(&keys)
+            }
+            ("list", _) => commands::env::ListCommand::try_from(config)?.run(),
+            ("use", Some(ss)) => {
+                commands::env::UseCommand::try_from(config)?.run(required(ss, "context_name")?)
+            }
             // If only `krs env` is specified, default to `krs env show`
             (_, _) => commands::env::ShowCommand::try_from(config)?.run(),
         },
@@ -245,13 +786,55 @@ pub fn dispatch(m: ArgMatches<'_>) -> Result<()> {
             let topic_name = required(s, "topic")?;
             commands::producer::ProducerCommand::try_from(config)?.run(topic_name)
         }
-        ("wait", Some(_)) => commands::wait::WaitCommand::try_from(config)?.run(),
+        ("health", Some(_)) => commands::health::HealthCommand::try_from(config)?.run(),
+        ("wait", Some(s)) => {
+            let message_matching = match optional_regex(s, "message_matching")? {
+                Some(pattern) => Some(commands::wait::MessageMatch {
+                    pattern,
+                    start: commands::wait::parse_start_offset(
+                        s.value_of("start_offset").unwrap_or("beginning"),
+                    )?,
+                }),
+                None => None,
+            };
+            let conditions = commands::wait::WaitConditions {
+                min_brokers: required(s, "min_brokers")?.parse::<usize>()?,
+                topics: optional_values(s, "topic"),
+                groups: optional_values(s, "wait_group"),
+                lag_below: s
+                    .value_of("lag_below")
+                    .map(|v| v.parse::<i64>())
+                    .transpose()?,
+                message_matching,
+            };
+            // Otherwise we'd be looking at the lag of a random group ID.
+            if conditions.lag_below.is_some() && config.group_id.is_none() {
+                return Err(Error::InvalidUsage(
+                    "-g/--group-id (or a context with a group_id) is required for `wait --lag-below`"
+                        .into(),
+                ));
+            }
+            // For `wait`, the timeout is how long to wait overall.
+            let timeout = config.timeout()?;
+            commands::wait::WaitCommand::try_from(config)?.run(&conditions, timeout)
+        }
+        ("__complete", Some(s)) => {
+            let kind = required(s, "kind")?.parse()?;
+            commands::completions::CompleteCommand::try_from(config)?.run(kind)
+        }
         (unhandled, _) => fail("", unhandled),
     }
 }
 
 // TODO: Probably use lazy_static! for this.
 pub fn make_parser<'a, 'b>() -> App<'a, 'b> {
+    make_visible_parser().subcommand(commands::completions::CompleteCommand::subcommand())
+}
+
+// Everything but the hidden `__complete` subcommand, for generating completion
+// scripts. clap's bash generator can't handle it anyway, since it splits the
+// paths of subcommands on `__`.
+pub(crate) fn make_visible_parser<'a, 'b>() -> App<'a, 'b> {
     // TODO: Can't embed extended Git information in version since it's not
     // available when running `cargo install` on some other machine.
     App::new("krs")
@@ -261,12 +844,33 @@ pub fn make_parser<'a, 'b>() -> App<'a, 'b> {
         .arg(args::brokers())
         .arg(args::group_id())
         .arg(args::zookeeper())
+        .arg(args::output())
+        .arg(args::context())
+        .arg(args::timeout())
+        .arg(args::retries())
+        .arg(args::verbose())
+        .arg(args::quiet())
+        .arg(args::debug())
+        .arg(args::tls())
+        .arg(args::tls_ca_file())
+        .arg(args::tls_cert_file())
+        .arg(args::tls_key_file())
+        .arg(args::tls_key_password())
+        .arg(args::tls_no_verify_hostname())
+        .arg(args::sasl_mechanism())
+        .arg(args::sasl_username())
+        .arg(args::sasl_password_file())
+        .arg(args::sasl_password_cmd())
+        .arg(args::rdkafka_property())
         .subcommand(
             SubCommand::with_name("env")
                 .about("Environment commands (defaults to `env show`).")
                 .long_about("Environment commands.\n\nIf no subcommand to `env` is specified, defaults to `env show`.")
                 .subcommand(commands::env::ShowCommand::subcommand())
-                .subcommand(commands::env::SetCommand::subcommand()),
+                .subcommand(commands::env::SetCommand::subcommand())
+                .subcommand(commands::env::UnsetCommand::subcommand())
+                .subcommand(commands::env::ListCommand::subcommand())
+                .subcommand(commands::env::UseCommand::subcommand()),
             )
         .subcommand(
             SubCommand::with_name("topics")
@@ -275,9 +879,38 @@ pub fn make_parser<'a, 'b>() -> App<'a, 'b> {
                 .subcommand(commands::topics::ListCommand::subcommand())
                 .subcommand(commands::topics::DescribeCommand::subcommand())
                 .subcommand(commands::topics::CreateCommand::subcommand())
-                .subcommand(commands::topics::DeleteCommand::subcommand()),
+                .subcommand(commands::topics::AddPartitionsCommand::subcommand())
+                .subcommand(commands::topics::DeleteCommand::subcommand())
+                .subcommand(
+                    SubCommand::with_name("config")
+                        .about("Topic config commands.")
+                        .subcommand(commands::topics::ConfigGetCommand::subcommand())
+                        .subcommand(commands::topics::ConfigSetCommand::subcommand())
+                        .subcommand(commands::topics::ConfigUnsetCommand::subcommand()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("brokers")
+                .about("Broker commands (defaults to `brokers list`).")
+                .long_about("Broker commands.\n\nIf no subcommand to `brokers` is specified, will default to `brokers list`.")
+                .subcommand(commands::brokers::ListCommand::subcommand())
+                .subcommand(
+                    SubCommand::with_name("config")
+                        .about("Broker config commands.")
+                        .subcommand(commands::brokers::ConfigGetCommand::subcommand())
+                        .subcommand(commands::brokers::ConfigSetCommand::subcommand())
+                        .subcommand(commands::brokers::ConfigUnsetCommand::subcommand()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cluster")
+                .about("Cluster commands (defaults to `cluster describe`).")
+                .long_about("Cluster commands.\n\nIf no subcommand to `cluster` is specified, will default to `cluster describe`.")
+                .subcommand(commands::cluster::DescribeCommand::subcommand()),
         )
         .subcommand(commands::consumer::ConsumerCommand::subcommand())
         .subcommand(commands::producer::ProducerCommand::subcommand())
+        .subcommand(commands::health::HealthCommand::subcommand())
         .subcommand(commands::wait::WaitCommand::subcommand())
+        .subcommand(commands::completions::CompletionsCommand::subcommand())
 }
@@ -0,0 +1,162 @@
+// Diagnostics on stderr, through the `log` macros: progress messages and
+// warnings by default, more with `-v`/`-vv`, and only errors with `-q`.
+// librdkafka's own logs go through the same logger (see `LoggingContext`),
+// but only show up with `-v` or `--debug`.
+use std::io::Write;
+
+use clap::ArgMatches;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rdkafka::client::ClientContext;
+use rdkafka::config::RDKafkaLogLevel;
+use rdkafka::consumer::ConsumerContext;
+use rdkafka::error::KafkaError;
+
+pub const LIBRDKAFKA_TARGET: &str = "librdkafka";
+
+// Debug contexts accepted by librdkafka's `debug` property.
+pub const DEBUG_CONTEXTS: &[&str] = &[
+    "generic",
+    "broker",
+    "topic",
+    "metadata",
+    "feature",
+    "queue",
+    "msg",
+    "protocol",
+    "cgrp",
+    "security",
+    "fetch",
+    "interceptor",
+    "plugin",
+    "consumer",
+    "admin",
+    "eos",
+    "all",
+];
+
+/// Which messages to print, from `-v`, `-q` and `--debug`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Verbosity {
+    pub level: LevelFilter,
+    pub librdkafka_level: LevelFilter,
+}
+
+impl From<&ArgMatches<'_>> for Verbosity {
+    fn from(args: &ArgMatches<'_>) -> Self {
+        let level = match args.occurrences_of("verbose") {
+            _ if args.is_present("quiet") => LevelFilter::Error,
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        };
+        // librdkafka logs debug contexts at the debug level, so make sure
+        // they show up even without `-v`.
+        let librdkafka_level = if args.is_present("debug") {
+            level.max(LevelFilter::Debug)
+        } else if level >= LevelFilter::Debug {
+            level
+        } else {
+            LevelFilter::Off
+        };
+        Self {
+            level,
+            librdkafka_level,
+        }
+    }
+}
+
+struct Logger {
+    verbosity: Verbosity,
+    color: bool,
+}
+
+impl Logger {
+    fn prefix(&self, level: Level) -> String {
+        let (name, color) = match level {
+            Level::Error => ("error: ", "\x1b[1;31m"),
+            Level::Warn => ("warning: ", "\x1b[1;33m"),
+            // Progress messages are printed as-is.
+            Level::Info => return String::new(),
+            Level::Debug => ("debug: ", "\x1b[2m"),
+            Level::Trace => ("trace: ", "\x1b[2m"),
+        };
+        if self.color {
+            format!("{}{}\x1b[0m", color, name)
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let target = metadata.target();
+        if target == LIBRDKAFKA_TARGET {
+            metadata.level() <= self.verbosity.librdkafka_level
+        } else {
+            // Skip the logs of dependencies, e.g. the rdkafka crate itself.
+            (target == "krs" || target.starts_with("krs::"))
+                && metadata.level() <= self.verbosity.level
+        }
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let source = if record.target() == LIBRDKAFKA_TARGET {
+            "librdkafka: "
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            std::io::stderr(),
+            "{}{}{}",
+            self.prefix(record.level()),
+            source,
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Sets up the logger for the whole process. Colors are only used if stderr
+/// is a terminal and `NO_COLOR` is not set.
+pub fn init(verbosity: Verbosity) {
+    let color = atty::is(atty::Stream::Stderr) && std::env::var_os("NO_COLOR").is_none();
+    let logger = Box::new(Logger { verbosity, color });
+    if log::set_logger(Box::leak(logger)).is_ok() {
+        log::set_max_level(verbosity.level.max(verbosity.librdkafka_level));
+    }
+}
+
+/// Client context for all Kafka clients, which sends librdkafka's logs and
+/// errors to the logger instead of the default context's targets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingContext;
+
+impl ClientContext for LoggingContext {
+    fn log(&self, level: RDKafkaLogLevel, fac: &str, log_message: &str) {
+        let level = match level {
+            RDKafkaLogLevel::Emerg
+            | RDKafkaLogLevel::Alert
+            | RDKafkaLogLevel::Critical
+            | RDKafkaLogLevel::Error => Level::Error,
+            RDKafkaLogLevel::Warning => Level::Warn,
+            RDKafkaLogLevel::Notice | RDKafkaLogLevel::Info => Level::Info,
+            RDKafkaLogLevel::Debug => Level::Debug,
+        };
+        log::log!(target: LIBRDKAFKA_TARGET, level, "{}: {}", fac, log_message);
+    }
+
+    // Most of these are retried by librdkafka, and the ones that aren't are
+    // returned by the requests that failed anyway.
+    fn error(&self, error: KafkaError, reason: &str) {
+        log::warn!(target: LIBRDKAFKA_TARGET, "{}: {}", error, reason);
+    }
+}
+
+impl ConsumerContext for LoggingContext {}
@@ -1,19 +1,32 @@
-use krs::{dispatch, make_parser};
+use krs::errors::exit_code;
+use krs::{dispatch, logging, make_parser, Verbosity};
 
-// krs env show|set
+// krs env show|set|list|use
 // krs topics list|create|delete|describe --brokers
 // krs groups list|describe
-// krs nodes show
+// krs brokers list
+// krs cluster describe
+// krs health
 // krs producer
 // krs consumer
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+// krs completions bash|zsh|fish|powershell
+fn main() {
     let app = make_parser();
-    let matches = app.get_matches();
-    let result = dispatch(matches);
+    let matches = match app.get_matches_safe() {
+        Ok(matches) => matches,
+        // --help and --version
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            eprintln!("{}", e.message);
+            std::process::exit(exit_code::USAGE);
+        }
+    };
 
-    if let Err(e) = result {
-        println!("error: {}", e);
+    logging::init(Verbosity::from(&matches));
+    if let Err(e) = dispatch(matches) {
+        log::error!("{}", e);
+        // So that scripts (e.g. `krs health` in cron) can detect failures,
+        // and tell them apart.
+        std::process::exit(e.exit_code());
     }
-
-    Ok(())
 }
@@ -0,0 +1,224 @@
+// Rendering layer shared by all commands, so that they all honour the
+// global `--output` flag.
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    JsonPretty,
+    Table,
+    Yaml,
+    Csv,
+}
+
+impl OutputFormat {
+    pub const VALUES: &'static [&'static str] = &["json", "json-pretty", "table", "yaml", "csv"];
+
+    // Tables are nicer for humans, but JSON is easier to pipe to `jq`.
+    pub fn detect() -> Self {
+        if atty::is(atty::Stream::Stdout) {
+            OutputFormat::Table
+        } else {
+            OutputFormat::Json
+        }
+    }
+
+    /// Prints `value` to stdout in this format.
+    pub fn print<T: Serialize>(self, value: &T) -> crate::Result<()> {
+        let rendered = self.render(value)?;
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        out.write_all(rendered.as_bytes())?;
+        if !rendered.ends_with('\n') {
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Prints a single item of a stream (e.g. consumed records). CSV rows
+    /// are printed without a header, since the stream has no end.
+    pub fn print_record<T: Serialize>(self, value: &T) -> crate::Result<()> {
+        match self {
+            OutputFormat::Csv => {
+                let value = serde_json::to_value(value)?;
+                let mut w = csv::Writer::from_writer(std::io::stdout());
+                match value.as_object() {
+                    Some(obj) => w.write_record(obj.values().map(cell))?,
+                    None => w.write_record(&[cell(&value)])?,
+                }
+                Ok(w.flush()?)
+            }
+            _ => self.print(value),
+        }
+    }
+
+    pub fn render<T: Serialize>(self, value: &T) -> crate::Result<String> {
+        match self {
+            OutputFormat::Json => Ok(serde_json::to_string(value)?),
+            OutputFormat::JsonPretty => Ok(serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+            OutputFormat::Table => Ok(render_table(&serde_json::to_value(value)?)),
+            OutputFormat::Csv => render_csv(&serde_json::to_value(value)?),
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "json-pretty" => Ok(OutputFormat::JsonPretty),
+            "table" => Ok(OutputFormat::Table),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(Error::InvalidUsage(format!(
+                "Expected one of {} for output, but got `{}`",
+                OutputFormat::VALUES.join("|"),
+                s
+            ))),
+        }
+    }
+}
+
+// Strings are printed without quotes, nested values as compact JSON.
+fn cell(v: &Value) -> String {
+    match v {
+        Value::Null => "".to_owned(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn is_table(v: &Value) -> bool {
+    match v {
+        Value::Array(rows) => !rows.is_empty() && rows.iter().all(Value::is_object),
+        _ => false,
+    }
+}
+
+// Column names in the order they first appear in the rows.
+fn columns(rows: &[Value]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for k in row.keys() {
+            if !columns.contains(k) {
+                columns.push(k.clone());
+            }
+        }
+    }
+    columns
+}
+
+fn write_aligned(out: &mut String, lines: &[Vec<String>]) {
+    let num_columns = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..num_columns)
+        .map(|i| {
+            lines
+                .iter()
+                .filter_map(|l| l.get(i))
+                .map(|c| c.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for line in lines {
+        let mut s = String::new();
+        for (i, c) in line.iter().enumerate() {
+            if i + 1 == line.len() {
+                s.push_str(c);
+            } else {
+                s.push_str(&format!("{:width$}  ", c, width = widths[i]));
+            }
+        }
+        out.push_str(s.trim_end());
+        out.push('\n');
+    }
+}
+
+fn write_rows(out: &mut String, rows: &[Value]) {
+    let columns = columns(rows);
+    let mut lines = vec![columns.iter().map(|c| c.to_uppercase()).collect()];
+    for row in rows {
+        lines.push(
+            columns
+                .iter()
+                .map(|c| row.get(c).map(cell).unwrap_or_default())
+                .collect(),
+        );
+    }
+    write_aligned(out, &lines);
+}
+
+// Objects are printed as KEY VALUE pairs, with nested lists of objects (e.g.
+// the partitions of a topic) printed as separate tables below them.
+fn write_object(out: &mut String, obj: &Map<String, Value>) {
+    let lines: Vec<Vec<String>> = obj
+        .iter()
+        .filter(|(_, v)| !is_table(v))
+        .map(|(k, v)| vec![format!("{}:", k.to_uppercase()), cell(v)])
+        .collect();
+    write_aligned(out, &lines);
+
+    for (k, v) in obj.iter().filter(|(_, v)| is_table(v)) {
+        out.push_str(&format!("\n{}:\n", k.to_uppercase()));
+        if let Value::Array(rows) = v {
+            write_rows(out, rows);
+        }
+    }
+}
+
+fn render_table(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Array(rows) if is_table(value) => write_rows(&mut out, rows),
+        Value::Array(items) => {
+            for item in items {
+                out.push_str(&cell(item));
+                out.push('\n');
+            }
+        }
+        Value::Object(obj) => write_object(&mut out, obj),
+        v => out.push_str(&cell(v)),
+    }
+    out
+}
+
+fn render_csv(value: &Value) -> crate::Result<String> {
+    let mut w = csv::Writer::from_writer(Vec::new());
+    match value {
+        Value::Array(rows) if is_table(value) => {
+            let columns = columns(rows);
+            w.write_record(&columns)?;
+            for row in rows {
+                w.write_record(
+                    columns
+                        .iter()
+                        .map(|c| row.get(c).map(cell).unwrap_or_default()),
+                )?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                w.write_record(&[cell(item)])?;
+            }
+        }
+        Value::Object(obj) => {
+            w.write_record(obj.keys())?;
+            w.write_record(obj.values().map(cell))?;
+        }
+        v => w.write_record(&[cell(v)])?,
+    }
+
+    let bytes = w
+        .into_inner()
+        .map_err(|e| Error::Generic(format!("Failed to write CSV: {}", e)))?;
+    String::from_utf8(bytes).map_err(|e| Error::Generic(format!("Failed to write CSV: {}", e)))
+}
@@ -0,0 +1,58 @@
+// Timeouts and retries for requests to the cluster, so that commands also
+// work against slow (e.g. cross-region) clusters.
+use std::fmt::Display;
+use std::time::Duration;
+
+use crate::{DEFAULT_RETRIES, DEFAULT_TIMEOUT};
+
+pub(crate) const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+pub(crate) const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// How long to wait for each request to the cluster (metadata, watermarks,
+/// admin and Zookeeper requests), and how many times to retry it if it fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Calls `f` with the timeout until it succeeds or the retries run out,
+    // backing off exponentially in between. Returns the last error.
+    pub(crate) fn run<T, E, F>(&self, what: &str, mut f: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnMut(Duration) -> Result<T, E>,
+    {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            match f(self.timeout) {
+                Ok(v) => return Ok(v),
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    log::warn!(
+                        "Failed to {} ({}), retrying in {:?} ({}/{}).",
+                        what,
+                        e,
+                        backoff,
+                        attempt,
+                        self.retries
+                    );
+                    std::thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
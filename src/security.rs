@@ -0,0 +1,411 @@
+use std::cell::RefCell;
+use std::process::Command;
+
+use clap::ArgMatches;
+use rdkafka::ClientConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result, Sourced};
+
+pub const TLS_ENV_KEY: &str = "KRS_TLS";
+pub const TLS_CA_FILE_ENV_KEY: &str = "KRS_TLS_CA_FILE";
+pub const TLS_CERT_FILE_ENV_KEY: &str = "KRS_TLS_CERT_FILE";
+pub const TLS_KEY_FILE_ENV_KEY: &str = "KRS_TLS_KEY_FILE";
+pub const TLS_KEY_PASSWORD_ENV_KEY: &str = "KRS_TLS_KEY_PASSWORD";
+pub const TLS_VERIFY_HOSTNAME_ENV_KEY: &str = "KRS_TLS_VERIFY_HOSTNAME";
+
+pub const SASL_MECHANISM_ENV_KEY: &str = "KRS_SASL_MECHANISM";
+pub const SASL_USERNAME_ENV_KEY: &str = "KRS_SASL_USERNAME";
+pub const SASL_PASSWORD_ENV_KEY: &str = "KRS_SASL_PASSWORD";
+pub const SASL_PASSWORD_FILE_ENV_KEY: &str = "KRS_SASL_PASSWORD_FILE";
+pub const SASL_PASSWORD_CMD_ENV_KEY: &str = "KRS_SASL_PASSWORD_CMD";
+
+pub const SASL_MECHANISMS: &[&str] = &["PLAIN", "SCRAM-SHA-256", "SCRAM-SHA-512", "OAUTHBEARER"];
+
+// librdkafka properties whose values are secrets, e.g. for `env show`.
+const SENSITIVE_PROPERTIES: &[&str] = &[
+    "ssl.key.password",
+    "ssl.key.pem",
+    "ssl.keystore.password",
+    "sasl.password",
+    "sasl.oauthbearer.config",
+];
+
+/// Whether the value of a librdkafka property (from `-X`, possibly scoped
+/// with e.g. `consumer.`) is a secret. Unknown properties that look like
+/// passwords or secrets count too, e.g. from newer librdkafka versions.
+pub fn is_sensitive_property(key: &str) -> bool {
+    let key = key.to_lowercase();
+    let unscoped = ["consumer.", "producer.", "admin."]
+        .iter()
+        .find(|prefix| key.starts_with(*prefix))
+        .map_or(key.as_str(), |prefix| &key[prefix.len()..]);
+    SENSITIVE_PROPERTIES.contains(&unscoped) || key.contains("password") || key.contains("secret")
+}
+
+fn parse_bool(sourced: &Sourced<String>) -> Result<bool> {
+    match sourced.value.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(Error::InvalidUsage(format!(
+            "Expected true or false, but got `{}` from {}",
+            sourced.value, sourced.source
+        ))),
+    }
+}
+
+/// TLS settings of a context in the user config file.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TlsSettings {
+    pub enabled: Option<bool>,
+    pub ca_file: Option<String>,
+    pub cert_file: Option<String>,
+    pub key_file: Option<String>,
+    pub key_password: Option<String>,
+    pub verify_hostname: Option<bool>,
+}
+
+/// TLS settings applied to every Kafka client. The flags are kept as they
+/// were given and only parsed when clients are created, so that e.g. `env
+/// unset` can fix a bad value in `.env`.
+#[derive(Debug, Default)]
+pub struct TlsConfig {
+    // Only needed if none of the files are given, e.g. when the broker
+    // certificates are signed by a CA the system already trusts.
+    pub enabled: Option<Sourced<String>>,
+    pub ca_file: Option<Sourced<String>>,
+    // Client certificate and key, for brokers that require client auth.
+    pub cert_file: Option<Sourced<String>>,
+    pub key_file: Option<Sourced<String>>,
+    pub key_password: Option<Sourced<String>>,
+    // Defaults to true.
+    pub verify_hostname: Option<Sourced<String>>,
+}
+
+impl TlsConfig {
+    // Reads the settings from env var-like keys, e.g. `KRS_TLS_CA_FILE`.
+    pub(crate) fn from_vars<F>(get: F) -> Self
+    where
+        F: Fn(&str) -> Option<Sourced<String>>,
+    {
+        Self {
+            enabled: get(TLS_ENV_KEY),
+            ca_file: get(TLS_CA_FILE_ENV_KEY),
+            cert_file: get(TLS_CERT_FILE_ENV_KEY),
+            key_file: get(TLS_KEY_FILE_ENV_KEY),
+            key_password: get(TLS_KEY_PASSWORD_ENV_KEY),
+            verify_hostname: get(TLS_VERIFY_HOSTNAME_ENV_KEY),
+        }
+    }
+
+    pub(crate) fn from_settings(settings: &TlsSettings, source: &str) -> Self {
+        let sourced = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map(|value| Sourced::new(source, value.to_owned()))
+        };
+        Self {
+            enabled: settings
+                .enabled
+                .map(|value| Sourced::new(source, value.to_string())),
+            ca_file: sourced(&settings.ca_file),
+            cert_file: sourced(&settings.cert_file),
+            key_file: sourced(&settings.key_file),
+            key_password: sourced(&settings.key_password),
+            verify_hostname: settings
+                .verify_hostname
+                .map(|value| Sourced::new(source, value.to_string())),
+        }
+    }
+
+    pub(crate) fn merge(self, rhs: Self) -> Self {
+        Self {
+            enabled: rhs.enabled.or(self.enabled),
+            ca_file: rhs.ca_file.or(self.ca_file),
+            cert_file: rhs.cert_file.or(self.cert_file),
+            key_file: rhs.key_file.or(self.key_file),
+            key_password: rhs.key_password.or(self.key_password),
+            verify_hostname: rhs.verify_hostname.or(self.verify_hostname),
+        }
+    }
+
+    // Giving any of the files implies TLS, unless it's explicitly disabled.
+    pub fn is_enabled(&self) -> Result<bool> {
+        match &self.enabled {
+            Some(enabled) => parse_bool(enabled),
+            None => {
+                Ok(self.ca_file.is_some() || self.cert_file.is_some() || self.key_file.is_some())
+            }
+        }
+    }
+
+    pub(crate) fn apply(&self, config: &mut ClientConfig) -> Result<()> {
+        if !self.is_enabled()? {
+            return Ok(());
+        }
+
+        if let Some(v) = &self.ca_file {
+            config.set("ssl.ca.location", &v.value);
+        }
+        if let Some(v) = &self.cert_file {
+            config.set("ssl.certificate.location", &v.value);
+        }
+        if let Some(v) = &self.key_file {
+            config.set("ssl.key.location", &v.value);
+        }
+        if let Some(v) = &self.key_password {
+            config.set("ssl.key.password", &v.value);
+        }
+        let verify_hostname = match &self.verify_hostname {
+            Some(v) => parse_bool(v)?,
+            None => true,
+        };
+        config.set(
+            "ssl.endpoint.identification.algorithm",
+            if verify_hostname { "https" } else { "none" },
+        );
+        Ok(())
+    }
+}
+
+impl From<&ArgMatches<'_>> for TlsConfig {
+    fn from(args: &ArgMatches<'_>) -> Self {
+        let sourced = |name: &str, source: &str| {
+            args.value_of(name)
+                .map(|value| Sourced::new(source, value.to_owned()))
+        };
+        Self {
+            enabled: if args.is_present("tls") {
+                Some(Sourced::new("--tls", "true".to_owned()))
+            } else {
+                None
+            },
+            ca_file: sourced("tls_ca_file", "--tls-ca-file"),
+            cert_file: sourced("tls_cert_file", "--tls-cert-file"),
+            key_file: sourced("tls_key_file", "--tls-key-file"),
+            key_password: sourced("tls_key_password", "--tls-key-password"),
+            verify_hostname: if args.is_present("tls_no_verify_hostname") {
+                Some(Sourced::new("--tls-no-verify-hostname", "false".to_owned()))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+/// Where to get the SASL password from. Passing it as a plain value is only
+/// possible through env vars, so that it doesn't end up in the shell history.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Password {
+    Plain(String),
+    File(String),
+    // Shell command that prints the password, e.g. `pass show kafka`.
+    Command(String),
+}
+
+impl Password {
+    pub fn resolve(&self) -> Result<String> {
+        match self {
+            Password::Plain(password) => Ok(password.to_owned()),
+            Password::File(path) => Ok(std::fs::read_to_string(path)
+                .map_err(|e| {
+                    Error::Generic(format!("Failed to read password from {}: {}", path, e))
+                })?
+                .trim_end_matches(&['\n', '\r'][..])
+                .to_owned()),
+            Password::Command(cmd) => {
+                let output = Command::new("sh").arg("-c").arg(cmd).output()?;
+                if !output.status.success() {
+                    return Err(Error::Generic(format!(
+                        "Password command `{}` failed with {}: {}",
+                        cmd,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                Ok(String::from_utf8_lossy(&output.stdout)
+                    .trim_end_matches(&['\n', '\r'][..])
+                    .to_owned())
+            }
+        }
+    }
+
+    // Never shows plain passwords, e.g. for `env show`.
+    pub fn masked(&self) -> String {
+        match self {
+            Password::Plain(_) => "********".to_owned(),
+            Password::File(path) => format!("(from file {})", path),
+            Password::Command(cmd) => format!("(from command `{}`)", cmd),
+        }
+    }
+}
+
+/// SASL settings of a context in the user config file. Plain passwords are
+/// not supported here, use a file or a command instead.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SaslSettings {
+    pub mechanism: Option<String>,
+    pub username: Option<String>,
+    pub password_file: Option<String>,
+    pub password_cmd: Option<String>,
+}
+
+/// SASL settings applied to every Kafka client.
+#[derive(Debug, Default)]
+pub struct SaslConfig {
+    // Only checked when clients are created, like the TLS flags.
+    pub mechanism: Option<Sourced<String>>,
+    pub username: Option<Sourced<String>>,
+    pub password: Option<Sourced<Password>>,
+    // The password command only runs once, even if a command creates
+    // several clients.
+    resolved_password: RefCell<Option<String>>,
+}
+
+fn validate_mechanism(sourced: &Sourced<String>) -> Result<String> {
+    let upper = sourced.value.to_uppercase();
+    if !SASL_MECHANISMS.contains(&upper.as_str()) {
+        return Err(Error::InvalidUsage(format!(
+            "Expected SASL mechanism to be one of {}, but got `{}` from {}",
+            SASL_MECHANISMS.join("|"),
+            sourced.value,
+            sourced.source
+        )));
+    }
+    Ok(upper)
+}
+
+impl SaslConfig {
+    // Reads the settings from env var-like keys, e.g. `KRS_SASL_USERNAME`.
+    // If several password keys are given, the command wins over the file,
+    // which wins over the plain password.
+    pub(crate) fn from_vars<F>(get: F) -> Self
+    where
+        F: Fn(&str) -> Option<Sourced<String>>,
+    {
+        let password_of = |key: &str, f: fn(String) -> Password| {
+            get(key).map(|s| Sourced {
+                source: s.source,
+                value: f(s.value),
+            })
+        };
+        Self {
+            mechanism: get(SASL_MECHANISM_ENV_KEY),
+            username: get(SASL_USERNAME_ENV_KEY),
+            password: password_of(SASL_PASSWORD_CMD_ENV_KEY, Password::Command)
+                .or_else(|| password_of(SASL_PASSWORD_FILE_ENV_KEY, Password::File))
+                .or_else(|| password_of(SASL_PASSWORD_ENV_KEY, Password::Plain)),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn from_settings(settings: &SaslSettings, source: &str) -> Self {
+        let sourced = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map(|value| Sourced::new(source, value.to_owned()))
+        };
+        Self {
+            mechanism: sourced(&settings.mechanism),
+            username: sourced(&settings.username),
+            password: settings
+                .password_cmd
+                .clone()
+                .map(Password::Command)
+                .or_else(|| settings.password_file.clone().map(Password::File))
+                .map(|password| Sourced::new(source, password)),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn merge(self, rhs: Self) -> Self {
+        Self {
+            mechanism: rhs.mechanism.or(self.mechanism),
+            username: rhs.username.or(self.username),
+            password: rhs.password.or(self.password),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mechanism.is_some()
+    }
+
+    fn password(&self) -> Result<Option<String>> {
+        let password = match &self.password {
+            Some(password) => password,
+            None => return Ok(None),
+        };
+        let mut resolved = self.resolved_password.borrow_mut();
+        if resolved.is_none() {
+            *resolved = Some(password.value.resolve()?);
+        }
+        Ok(resolved.clone())
+    }
+
+    pub fn apply(&self, config: &mut ClientConfig) -> Result<()> {
+        let mechanism = match &self.mechanism {
+            Some(mechanism) => validate_mechanism(mechanism)?,
+            None => return Ok(()),
+        };
+
+        config.set("sasl.mechanism", &mechanism);
+        if mechanism == "OAUTHBEARER" {
+            // librdkafka can only create unsecured tokens by itself (and only
+            // if asked to), which is enough for development clusters.
+            // Anything else needs a token refresh callback.
+            config.set("enable.sasl.oauthbearer.unsecure.jwt", "true");
+            if let Some(username) = &self.username {
+                config.set(
+                    "sasl.oauthbearer.config",
+                    &format!("principal={}", username.value),
+                );
+            }
+            return Ok(());
+        }
+
+        if let Some(username) = &self.username {
+            config.set("sasl.username", &username.value);
+        }
+        if let Some(password) = self.password()? {
+            config.set("sasl.password", &password);
+        }
+        Ok(())
+    }
+}
+
+impl From<&ArgMatches<'_>> for SaslConfig {
+    fn from(args: &ArgMatches<'_>) -> Self {
+        let sourced = |name: &str, source: &str| {
+            args.value_of(name)
+                .map(|value| Sourced::new(source, value.to_owned()))
+        };
+        Self {
+            // Already validated by clap.
+            mechanism: sourced("sasl_mechanism", "--sasl-mechanism").map(|s| Sourced {
+                source: s.source,
+                value: s.value.to_uppercase(),
+            }),
+            username: sourced("sasl_username", "--sasl-username"),
+            password: args
+                .value_of("sasl_password_cmd")
+                .map(|cmd| Sourced::new("--sasl-password-cmd", Password::Command(cmd.into())))
+                .or_else(|| {
+                    args.value_of("sasl_password_file").map(|path| {
+                        Sourced::new("--sasl-password-file", Password::File(path.into()))
+                    })
+                }),
+            ..Default::default()
+        }
+    }
+}
+
+// The value of `security.protocol` for the given settings.
+pub(crate) fn security_protocol(tls: &TlsConfig, sasl: &SaslConfig) -> Result<&'static str> {
+    Ok(match (tls.is_enabled()?, sasl.is_enabled()) {
+        (false, false) => "plaintext",
+        (true, false) => "ssl",
+        (false, true) => "sasl_plaintext",
+        (true, true) => "sasl_ssl",
+    })
+}
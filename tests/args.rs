@@ -1,9 +1,13 @@
 #[cfg(test)]
+use std::convert::TryFrom;
 use std::env;
 use std::fs::File;
 use std::io::Write;
+use std::time::Duration;
 
-use krs::{make_parser, Config};
+use log::LevelFilter;
+
+use krs::{dispatch, make_parser, Config, Error, RetryPolicy, Sourced, Verbosity};
 
 // TODO: https://github.com/rust-lang/rust/issues/46379
 mod util;
@@ -37,6 +41,47 @@ fn test_global_args() {
     );
 }
 
+#[test]
+fn test_multiple_brokers() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "kafka-1:9092",
+            "-b",
+            "kafka-2:9092,SSL://kafka-3:9093",
+            "-b",
+            "[::1]:9092",
+            "env",
+            "show",
+        ])
+        .unwrap();
+    let brokers = Config::from(&matches).brokers.unwrap();
+    assert_eq!(
+        brokers.value,
+        "kafka-1:9092,kafka-2:9092,SSL://kafka-3:9093,[::1]:9092"
+    );
+    assert_eq!(brokers.source, "-b/--brokers");
+
+    for invalid in &[
+        "localhost",
+        "localhost:",
+        ":9092",
+        "localhost:http",
+        "::1:9092",
+    ] {
+        let parser = make_parser();
+        assert!(
+            parser
+                .get_matches_from_safe(&["./binary", "-b", invalid, "env", "show"])
+                .is_err(),
+            "Expected `{}` to be rejected",
+            invalid
+        );
+    }
+}
+
 #[test]
 fn test_sourced_configs_precedence() {
     // Since current directory might have .env
@@ -47,54 +92,359 @@ fn test_sourced_configs_precedence() {
         File::create("./.env").unwrap();
     }
 
-    env::set_var("KRS_BROKERS", "kafka_from_env");
+    env::set_var("KRS_BROKERS", "kafka_from_env:9092");
 
     let parser = make_parser();
     let config = Config::init(
         &parser
             .get_matches_from_safe(&["./binary", "env", "show"])
             .unwrap(),
-    );
+    )
+    .unwrap();
 
     let actual = config.brokers.unwrap();
     assert_eq!(actual.source, "env var (KRS_BROKERS)".to_owned());
-    assert_eq!(actual.value, "kafka_from_env".to_owned());
+    assert_eq!(actual.value, "kafka_from_env:9092".to_owned());
 
     let parser = make_parser();
     let config = Config::init(
         &parser
-            .get_matches_from_safe(&["./binary", "env", "show", "-b", "kafka_from_cli"])
+            .get_matches_from_safe(&["./binary", "env", "show", "-b", "kafka_from_cli:9092"])
             .unwrap(),
-    );
+    )
+    .unwrap();
 
     let actual = config.brokers.unwrap();
     assert_eq!(actual.source, "-b/--brokers".to_owned());
-    assert_eq!(actual.value, "kafka_from_cli".to_owned());
+    assert_eq!(actual.value, "kafka_from_cli:9092".to_owned());
 
     let mut f = File::create("./.env").unwrap();
-    f.write_all(b"KRS_BROKERS=kafka_from_dotenv\n").unwrap();
+    f.write_all(b"KRS_BROKERS=kafka_from_dotenv:9092\n")
+        .unwrap();
 
     let parser = make_parser();
     let config = Config::init(
         &parser
             .get_matches_from_safe(&["./binary", "env", "show"])
             .unwrap(),
-    );
+    )
+    .unwrap();
 
     let actual = config.brokers.unwrap();
     assert_eq!(actual.source, ".env file (KRS_BROKERS)".to_owned());
-    assert_eq!(actual.value, "kafka_from_dotenv".to_owned());
+    assert_eq!(actual.value, "kafka_from_dotenv:9092".to_owned());
 
     let parser = make_parser();
     let config = Config::init(
         &parser
-            .get_matches_from_safe(&["./binary", "env", "show", "-b", "kafka_from_cli"])
+            .get_matches_from_safe(&["./binary", "env", "show", "-b", "kafka_from_cli:9092"])
             .unwrap(),
-    );
+    )
+    .unwrap();
 
     let actual = config.brokers.unwrap();
     assert_eq!(actual.source, "-b/--brokers".to_owned());
-    assert_eq!(actual.value, "kafka_from_cli".to_owned());
+    assert_eq!(actual.value, "kafka_from_cli:9092".to_owned());
+}
+
+#[test]
+fn test_invalid_durations_are_rejected() {
+    for timeout in &["5x", "s", "1.5s", "99999999999999999h"] {
+        let parser = make_parser();
+        let matches = parser
+            .get_matches_from_safe(&[
+                "./binary",
+                "-b",
+                "localhost:9092",
+                "wait",
+                "--timeout",
+                timeout,
+            ])
+            .unwrap();
+
+        match dispatch(matches) {
+            Err(Error::InvalidUsage(_)) => (),
+            other => panic!(
+                "Expected InvalidUsage for `{}`, but got {:?}",
+                timeout, other
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_wait_lag_below_requires_group_id() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9092",
+            "wait",
+            "--lag-below",
+            "10",
+        ])
+        .unwrap();
+
+    match dispatch(matches) {
+        Err(Error::InvalidUsage(_)) => (),
+        other => panic!("Expected InvalidUsage, but got {:?}", other),
+    }
+
+    // Global args should be visible from the subcommand too.
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&["./binary", "-g", "my-group", "wait", "--lag-below", "10"])
+        .unwrap();
+    assert_eq!(
+        matches
+            .subcommand_matches("wait")
+            .and_then(|m| m.value_of("group-id")),
+        Some("my-group")
+    );
+}
+
+#[test]
+fn test_wait_message_matching_args() {
+    // --message-matching needs a topic to look in.
+    let parser = make_parser();
+    assert!(parser
+        .get_matches_from_safe(&["./binary", "wait", "--message-matching", "ready"])
+        .is_err());
+
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9092",
+            "wait",
+            "-t",
+            "my-topic",
+            "--message-matching",
+            "ready",
+            "--from",
+            "latest",
+        ])
+        .unwrap();
+    match dispatch(matches) {
+        Err(Error::InvalidUsage(_)) => (),
+        other => panic!("Expected InvalidUsage, but got {:?}", other),
+    }
+}
+
+#[test]
+fn test_tls_args() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&["./binary", "-b", "localhost:9093", "topics", "list"])
+        .unwrap();
+    assert!(!Config::from(&matches).tls.is_enabled().unwrap());
+
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9093",
+            "topics",
+            "list",
+            "--tls",
+            "--tls-no-verify-hostname",
+        ])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert!(config.tls.is_enabled().unwrap());
+    assert_eq!(config.tls.verify_hostname.as_ref().unwrap().value, "false");
+    // Creating clients fails if librdkafka was built without TLS support.
+    assert_ok!(krs::commands::topics::ListCommand::try_from(config));
+
+    // Any of the files implies TLS.
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&["./binary", "--tls-ca-file", "/etc/ca.pem", "env", "show"])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert!(config.tls.is_enabled().unwrap());
+    assert_eq!(config.tls.ca_file.unwrap().source, "--tls-ca-file");
+}
+
+#[test]
+fn test_rdkafka_properties() {
+    // Valid for consumers, so it doesn't break other tests in this file.
+    env::set_var("KRS_RDKAFKA_CONSUMER_FETCH_WAIT_MAX_MS", "100");
+
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9092",
+            "-X",
+            "client.id=krs-test",
+            "-X",
+            "producer.no.such.property=1",
+            "topics",
+            "list",
+        ])
+        .unwrap();
+    let config = Config::init(&matches).unwrap();
+    let property = &config.rdkafka_properties["consumer.fetch.wait.max.ms"];
+    assert_eq!(property.value, "100");
+    assert_eq!(
+        property.source,
+        "env var (KRS_RDKAFKA_CONSUMER_FETCH_WAIT_MAX_MS)"
+    );
+    assert_eq!(config.rdkafka_properties["client.id"].source, "-X");
+    // Producer properties are not passed to consumers.
+    assert_ok!(krs::commands::topics::ListCommand::try_from(config));
+
+    // Unknown properties are rejected by librdkafka.
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9092",
+            "-X",
+            "no.such.property=1",
+            "topics",
+            "list",
+        ])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert!(krs::commands::topics::ListCommand::try_from(config).is_err());
+
+    let parser = make_parser();
+    assert!(parser
+        .get_matches_from_safe(&["./binary", "-X", "client.id", "env", "show"])
+        .is_err());
+}
+
+#[test]
+fn test_timeout_and_retries() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&["./binary", "env", "show"])
+        .unwrap();
+    assert_eq!(
+        Config::from(&matches).retry_policy().unwrap(),
+        RetryPolicy::default()
+    );
+
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "--timeout",
+            "30s",
+            "topics",
+            "list",
+            "--retries",
+            "3",
+        ])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert_eq!(config.timeout().unwrap(), Some(Duration::from_secs(30)));
+    assert_eq!(
+        config.retry_policy().unwrap(),
+        RetryPolicy {
+            timeout: Duration::from_secs(30),
+            retries: 3,
+        }
+    );
+
+    // Invalid values from other sources are only rejected when used.
+    let config = Config {
+        retries: Some(Sourced::new("unit_test", "-1".to_owned())),
+        ..test_config()
+    };
+    match krs::commands::topics::ListCommand::try_from(config) {
+        Err(Error::InvalidUsage(_)) => (),
+        _ => panic!("Expected InvalidUsage for negative retries"),
+    }
+
+    let parser = make_parser();
+    assert!(parser
+        .get_matches_from_safe(&["./binary", "--retries", "x", "env", "show"])
+        .is_err());
+}
+
+#[test]
+fn test_verbosity() {
+    let verbosity = |args: &[&str]| {
+        let matches = make_parser().get_matches_from_safe(args).unwrap();
+        let verbosity = Verbosity::from(&matches);
+        (verbosity.level, verbosity.librdkafka_level)
+    };
+    assert_eq!(
+        verbosity(&["./binary", "env", "show"]),
+        (LevelFilter::Info, LevelFilter::Off)
+    );
+    assert_eq!(
+        verbosity(&["./binary", "-v", "env", "show"]),
+        (LevelFilter::Debug, LevelFilter::Debug)
+    );
+    assert_eq!(
+        verbosity(&["./binary", "env", "show", "-vv"]),
+        (LevelFilter::Trace, LevelFilter::Trace)
+    );
+    assert_eq!(
+        verbosity(&["./binary", "-q", "env", "show"]),
+        (LevelFilter::Error, LevelFilter::Off)
+    );
+    assert_eq!(
+        verbosity(&["./binary", "-q", "env", "show", "--debug", "broker"]),
+        (LevelFilter::Error, LevelFilter::Debug)
+    );
+    assert!(make_parser()
+        .get_matches_from_safe(&["./binary", "-v", "-q", "env", "show"])
+        .is_err());
+}
+
+#[test]
+fn test_debug_contexts() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "--debug",
+            "broker,protocol",
+            "--debug",
+            "fetch",
+            "topics",
+            "list",
+        ])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert_eq!(
+        config.rdkafka_properties["debug"].value,
+        "broker,protocol,fetch"
+    );
+    assert_eq!(config.rdkafka_properties["debug"].source, "--debug");
+
+    // `-X debug=...` wins.
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "--debug",
+            "broker",
+            "-X",
+            "debug=all",
+            "env",
+            "show",
+        ])
+        .unwrap();
+    assert_eq!(
+        Config::from(&matches).rdkafka_properties["debug"].value,
+        "all"
+    );
+
+    let parser = make_parser();
+    assert!(parser
+        .get_matches_from_safe(&["./binary", "--debug", "no-such-context", "env", "show"])
+        .is_err());
 }
 
 // test that running krs prints usage
@@ -0,0 +1,207 @@
+#[cfg(test)]
+use std::convert::TryFrom;
+
+use std::time::Duration;
+
+use futures::Future;
+use rand::random;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{Offset, TopicPartitionList};
+
+use krs::commands::{brokers, cluster, health, topics, wait};
+use krs::Error;
+
+// TODO: https://github.com/rust-lang/rust/issues/46379
+mod util;
+pub use util::*;
+
+#[test]
+fn test_list_brokers_ok() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = brokers::ListCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run());
+
+    let cmd = brokers::ListCommand::try_from(test_config_brokers_only()).unwrap();
+    assert_ok!(cmd.run());
+}
+
+#[test]
+fn test_describe_cluster_ok() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = cluster::DescribeCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run());
+}
+
+#[test]
+fn test_set_get_and_unset_broker_configs() {
+    use brokers::{BrokerSelection, ConfigGetCommand, ConfigSetCommand, ConfigUnsetCommand};
+
+    // Assumes that a Kafka broker with ID 1 is running at localhost:9092
+    let cmd = ConfigSetCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(
+        BrokerSelection::One(1),
+        &[("log.cleaner.threads".into(), "2".into())]
+    ));
+
+    let cmd = ConfigGetCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(1));
+
+    let cmd = ConfigUnsetCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(BrokerSelection::All, &["log.cleaner.threads".into()]));
+}
+
+#[test]
+fn test_health_check_runs() {
+    // Assumes that a Kafka broker is running at localhost:9092. A single
+    // broker setup always has topics with replication factor 1, so only check
+    // that the report is produced without Kafka errors.
+    let cmd = health::HealthCommand::try_from(test_config()).unwrap();
+    if let Err(krs::Error::Kafka(e)) = cmd.run() {
+        panic!("Unexpected Kafka error: {:?}", e);
+    }
+}
+
+#[test]
+fn test_wait_for_cluster_ok() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = wait::WaitCommand::try_from(test_config()).unwrap();
+    let conditions = wait::WaitConditions {
+        min_brokers: 1,
+        topics: vec!["__consumer_offsets".into()],
+        ..Default::default()
+    };
+    assert_ok!(cmd.run(&conditions, Some(Duration::from_secs(30))));
+}
+
+#[test]
+fn test_wait_times_out() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = wait::WaitCommand::try_from(test_config()).unwrap();
+    let conditions = wait::WaitConditions {
+        min_brokers: 100,
+        ..Default::default()
+    };
+    // Not just a connection error.
+    match cmd.run(&conditions, Some(Duration::from_secs(1))) {
+        Err(Error::Timeout(msg)) => assert!(msg.contains("expected at least 100"), "{}", msg),
+        other => panic!("Expected Timeout, but got {:?}", other),
+    }
+}
+
+// Creates a topic with one partition and produces a record to it.
+fn topic_with_record(payload: &str) -> String {
+    let topic_name = format!("krs-topic-{}", random::<u64>());
+    let cmd = topics::CreateCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&topic_name, 1, 1, &Default::default()));
+
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", "localhost:9092")
+        .create()
+        .unwrap();
+    let record = FutureRecord::<(), _>::to(&topic_name).payload(payload);
+    assert_ok!(producer.send(record, 5000).wait().unwrap());
+    topic_name
+}
+
+// Commits the end of each partition of the topic for the group.
+fn commit_end_offsets(topic_name: &str, group_id: &str) {
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", "localhost:9092")
+        .set("group.id", group_id)
+        .create()
+        .unwrap();
+    let (_, high) = consumer
+        .fetch_watermarks(topic_name, 0, Duration::from_secs(5))
+        .unwrap();
+    let mut tpl = TopicPartitionList::new();
+    tpl.add_partition_offset(topic_name, 0, Offset::Offset(high));
+    assert_ok!(consumer.commit(&tpl, CommitMode::Sync));
+}
+
+#[test]
+fn test_wait_for_lag() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let topic_name = topic_with_record("krs-lag");
+    let group_id = format!("krs-group-{}", random::<u64>());
+    let config = || krs::Config {
+        group_id: Some(krs::Sourced::new("unit_test", group_id.clone())),
+        ..test_config()
+    };
+    let conditions = wait::WaitConditions {
+        topics: vec![topic_name.clone()],
+        lag_below: Some(1),
+        ..Default::default()
+    };
+
+    // Nothing consumed yet, so the lag is 1.
+    let cmd = wait::WaitCommand::try_from(config()).unwrap();
+    match cmd.run(&conditions, Some(Duration::from_secs(1))) {
+        Err(Error::Timeout(msg)) => assert!(msg.contains("Group has lag 1"), "{}", msg),
+        other => panic!("Expected Timeout, but got {:?}", other),
+    }
+
+    commit_end_offsets(&topic_name, &group_id);
+    let cmd = wait::WaitCommand::try_from(config()).unwrap();
+    assert_ok!(cmd.run(&conditions, Some(Duration::from_secs(30))));
+}
+
+#[test]
+fn test_wait_for_lag_of_unknown_group() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let config = krs::Config {
+        group_id: Some(krs::Sourced::new(
+            "unit_test",
+            "krs-group-without-offsets".into(),
+        )),
+        ..test_config()
+    };
+    let cmd = wait::WaitCommand::try_from(config).unwrap();
+    let conditions = wait::WaitConditions {
+        lag_below: Some(1),
+        ..Default::default()
+    };
+    // The group never committed anything, so this should time out.
+    match cmd.run(&conditions, Some(Duration::from_secs(1))) {
+        Err(Error::Timeout(msg)) => assert!(msg.contains("not committed any offsets"), "{}", msg),
+        other => panic!("Expected Timeout, but got {:?}", other),
+    }
+}
+
+#[test]
+fn test_wait_for_message() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let payload = format!("krs-record-{}", random::<u64>());
+    let topic_name = topic_with_record(&payload);
+    let cmd = wait::WaitCommand::try_from(test_config()).unwrap();
+    let conditions = wait::WaitConditions {
+        topics: vec![topic_name],
+        message_matching: Some(wait::MessageMatch {
+            pattern: regex::Regex::new(&format!("^{}$", payload)).unwrap(),
+            start: wait::parse_start_offset("beginning").unwrap(),
+        }),
+        ..Default::default()
+    };
+    assert_ok!(cmd.run(&conditions, Some(Duration::from_secs(30))));
+}
+
+#[test]
+fn test_wait_for_message_times_out() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let topic_name = topic_with_record("krs-other-record");
+    let cmd = wait::WaitCommand::try_from(test_config()).unwrap();
+    let conditions = wait::WaitConditions {
+        topics: vec![topic_name],
+        message_matching: Some(wait::MessageMatch {
+            pattern: regex::Regex::new("^krs-no-such-record$").unwrap(),
+            start: wait::parse_start_offset("beginning").unwrap(),
+        }),
+        ..Default::default()
+    };
+    // The cluster is ready, but the record never shows up.
+    match cmd.run(&conditions, Some(Duration::from_secs(5))) {
+        Err(Error::Timeout(msg)) => assert!(msg.contains("record matching"), "{}", msg),
+        other => panic!("Expected Timeout, but got {:?}", other),
+    }
+}
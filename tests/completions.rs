@@ -0,0 +1,50 @@
+#[cfg(test)]
+use std::convert::TryFrom;
+
+use clap::Shell;
+use krs::commands::completions::{script, CompleteCommand, CompletionKind};
+use krs::{make_parser, Config, Error};
+
+// TODO: https://github.com/rust-lang/rust/issues/46379
+mod util;
+pub use util::*;
+
+#[test]
+fn test_completion_scripts() {
+    let bash = script(Shell::Bash);
+    assert!(bash.contains("_krs()"));
+    assert!(bash.contains("krs __complete topics"));
+    assert!(bash
+        .trim_end()
+        .ends_with("complete -F _krs_dynamic -o bashdefault -o default krs"));
+
+    let zsh = script(Shell::Zsh);
+    assert!(zsh.starts_with("#compdef krs\n"));
+    assert!(zsh.contains("'-t+[Kafka topic name]: :_krs_topics' \\"));
+    assert!(zsh.contains("'--group-id=[Kafka consumer group ID]: :_krs_groups' \\"));
+    assert!(zsh.trim_end().ends_with("_krs \"$@\""));
+
+    assert!(script(Shell::Fish).contains("(krs __complete groups 2>/dev/null)"));
+    assert!(script(Shell::PowerShell).contains("Register-ArgumentCompleter"));
+
+    // `__complete` isn't offered, but still parsed.
+    assert!(!script(Shell::PowerShell).contains("Lists topic names"));
+    let matches = make_parser()
+        .get_matches_from_safe(&["./binary", "__complete", "topics"])
+        .unwrap();
+    let kind: CompletionKind = matches
+        .subcommand_matches("__complete")
+        .and_then(|m| m.value_of("kind"))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(kind, CompletionKind::Topics);
+    assert!(make_parser()
+        .get_matches_from_safe(&["./binary", "__complete", "brokers"])
+        .is_err());
+
+    match CompleteCommand::try_from(Config::default()) {
+        Err(Error::InvalidUsage(_)) => (),
+        _ => panic!("Expected InvalidUsage without brokers"),
+    }
+}
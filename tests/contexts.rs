@@ -0,0 +1,155 @@
+#[cfg(test)]
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+
+use krs::commands::env::UseCommand;
+use krs::contexts::CONFIG_PATH_ENV_KEY;
+use krs::{dispatch, make_parser, Config, UserConfig};
+
+// TODO: https://github.com/rust-lang/rust/issues/46379
+mod util;
+pub use util::*;
+
+const USER_CONFIG: &str = r#"
+# Clusters I work with.
+current_context = "dev"
+
+# Local cluster from docker-compose.
+[contexts.dev]
+brokers = "dev-kafka:9092"
+
+[contexts.staging]
+brokers = "staging-kafka:9092"
+zookeeper = "staging-zk:2181"
+group_id = "staging-group"
+
+[contexts.staging.tls]
+ca_file = "/etc/kafka/staging-ca.pem"
+
+[contexts.staging.rdkafka]
+"client.id" = "krs-staging"
+
+[contexts.broken]
+brokers = "kafka-1:9092,kafka-2"
+"#;
+
+// Everything is in one test, since the config file path is set through an
+// env var that is shared by all tests in this file.
+#[test]
+fn test_contexts() {
+    let path = env::temp_dir().join(format!("krs-config-{}.toml", rand::random::<u32>()));
+    fs::write(&path, USER_CONFIG).unwrap();
+    env::set_var(CONFIG_PATH_ENV_KEY, &path);
+    let _guard = scopeguard::guard((), |_| {
+        fs::remove_file(&path).ok();
+    });
+
+    // The current context is used by default.
+    let matches = make_parser()
+        .get_matches_from_safe(&["./binary", "env", "show"])
+        .unwrap();
+    let config = Config::init(&matches).unwrap();
+    assert_eq!(config.brokers.unwrap().value, "dev-kafka:9092");
+    assert_eq!(config.context.unwrap().value, "dev");
+
+    // --context selects another one, but CLI args still win.
+    let matches = make_parser()
+        .get_matches_from_safe(&[
+            "./binary",
+            "--context",
+            "staging",
+            "-b",
+            "localhost:9092",
+            "env",
+            "show",
+        ])
+        .unwrap();
+    let config = Config::init(&matches).unwrap();
+    let brokers = config.brokers.unwrap();
+    assert_eq!(brokers.value, "localhost:9092");
+    assert_eq!(brokers.source, "-b/--brokers");
+    let zookeeper = config.zookeeper.unwrap();
+    assert_eq!(zookeeper.value, "staging-zk:2181");
+    assert_eq!(zookeeper.source, "context staging");
+    assert_eq!(
+        config.group_id.map(|g| g.value),
+        Some("staging-group".to_owned())
+    );
+    assert!(config.tls.is_enabled().unwrap());
+    assert_eq!(config.rdkafka_properties["client.id"].value, "krs-staging");
+
+    let matches = make_parser()
+        .get_matches_from_safe(&["./binary", "--context", "prod", "env", "show"])
+        .unwrap();
+    assert!(Config::init(&matches).is_err());
+
+    // Brokers from the config file are validated too, once they're used.
+    let matches = make_parser()
+        .get_matches_from_safe(&["./binary", "--context", "broken", "topics", "list"])
+        .unwrap();
+    match dispatch(matches) {
+        Err(krs::Error::InvalidUsage(msg)) => assert!(msg.contains("context broken"), "{}", msg),
+        other => panic!("Expected InvalidUsage, but got {:?}", other),
+    }
+
+    // `env use` switches the current context in the file.
+    let cmd = UseCommand::try_from(Config::default()).unwrap();
+    assert_ok!(cmd.run("staging"));
+    let user_config = UserConfig::load().unwrap();
+    assert_eq!(user_config.current_context, Some("staging".to_owned()));
+    // Only the current context changes, comments and order are kept.
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        USER_CONFIG.replace("current_context = \"dev\"", "current_context = \"staging\"")
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    assert_eq!(user_config.contexts.len(), 3);
+    assert_eq!(
+        user_config.contexts["staging"].rdkafka["client.id"],
+        "krs-staging"
+    );
+
+    assert!(cmd.run("prod").is_err());
+
+    // Broken brokers in the current context or the .env file don't get in
+    // the way of fixing them.
+    let dir = env::temp_dir().join(format!("krs-contexts-{}", rand::random::<u32>()));
+    fs::create_dir(&dir).unwrap();
+    env::set_current_dir(&dir).unwrap();
+    let _dir_guard = scopeguard::guard((), |_| {
+        fs::remove_dir_all(&dir).ok();
+    });
+    fs::write(".env", "KRS_BROKERS=kafka-1:9092,kafka-2\n").unwrap();
+    assert_ok!(cmd.run("broken"));
+    let run = |args: &[&str]| dispatch(make_parser().get_matches_from_safe(args).unwrap());
+    assert_ok!(run(&["./binary", "env", "show"]));
+    assert_ok!(run(&["./binary", "env", "list"]));
+    assert_ok!(run(&["./binary", "env", "unset", "brokers"]));
+    assert_eq!(fs::read_to_string(".env").unwrap(), "");
+    assert_ok!(run(&["./binary", "env", "use", "dev"]));
+    assert_eq!(
+        UserConfig::load().unwrap().current_context,
+        Some("dev".to_owned())
+    );
+    // The same goes for TLS flags and the SASL mechanism, which are only
+    // checked once used.
+    for (key, value) in &[
+        ("tls", "KRS_TLS=maybe"),
+        ("sasl_mechanism", "KRS_SASL_MECHANISM=GSSAPI"),
+    ] {
+        fs::write(".env", format!("{}\n", value)).unwrap();
+        assert_ok!(run(&["./binary", "env", "show"]));
+        match run(&["./binary", "topics", "list"]) {
+            Err(krs::Error::InvalidUsage(msg)) => assert!(msg.contains(".env file"), "{}", msg),
+            other => panic!("Expected InvalidUsage, but got {:?}", other),
+        }
+        assert_ok!(run(&["./binary", "env", "unset", key]));
+        assert_eq!(fs::read_to_string(".env").unwrap(), "");
+    }
+}
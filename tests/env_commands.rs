@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 #[cfg(test)]
 use std::convert::TryFrom;
 use std::env;
@@ -5,7 +6,7 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
-use krs::{BROKERS_ENV_KEY, ZOOKEEPER_ENV_KEY};
+use krs::{Sourced, BROKERS_ENV_KEY, ZOOKEEPER_ENV_KEY};
 
 // TODO: https://github.com/rust-lang/rust/issues/46379
 mod util;
@@ -17,13 +18,19 @@ fn readlines(f: File) -> Vec<String> {
 
 #[test]
 fn test_set_env_does_not_destroy_existing_dotenv_file() -> std::io::Result<()> {
-    use krs::commands::env::SetCommand;
+    use krs::commands::env::{SetCommand, UnsetCommand};
 
     let dir = env::temp_dir();
     env::set_current_dir(dir).unwrap();
     {
         let mut f = File::create(".env").unwrap();
-        f.write_all(b"TEST_KEY=test_value")
+        f.write_all(
+            b"# Local settings\n\
+              TEST_KEY=test_value\n\
+              QUOTED_KEY='quoted value' # comment\n\
+              KRS_BROKERS_OLD=old:9092\n\
+              KRS_ZOOKEEPER=old:2181\n",
+        )
     }?;
 
     assert_ok!(SetCommand::try_from(test_config_brokers_only())
@@ -47,5 +54,40 @@ fn test_set_env_does_not_destroy_existing_dotenv_file() -> std::io::Result<()> {
     assert_some!(lines.iter().find(|x| x.starts_with(BROKERS_ENV_KEY)));
     assert_some!(lines.iter().find(|x| x.starts_with(ZOOKEEPER_ENV_KEY)));
 
+    // Unrelated lines are kept as they are, and existing keys are replaced
+    // in place.
+    assert_eq!(
+        lines,
+        vec![
+            "# Local settings",
+            "TEST_KEY=test_value",
+            "QUOTED_KEY='quoted value' # comment",
+            "KRS_BROKERS_OLD=old:9092",
+            "KRS_ZOOKEEPER=localhost:2181",
+            "KRS_BROKERS=localhost:9092",
+        ]
+    );
+
+    // Values are quoted so that they read back the same.
+    let config = krs::Config {
+        group_id: Some(Sourced::new("unit_test", "it's a $group".to_owned())),
+        ..Default::default()
+    };
+    assert_ok!(SetCommand::try_from(config).unwrap().run());
+    #[allow(deprecated)]
+    let vars: HashMap<String, String> = dotenv::from_path_iter(".env")
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+    assert_eq!(vars["KRS_GROUP_ID"], "it's a $group");
+    assert_eq!(vars["QUOTED_KEY"], "quoted value");
+
+    assert_ok!(UnsetCommand.run(&["brokers".to_owned(), "KRS_GROUP_ID".to_owned()]));
+    let lines: Vec<String> = readlines(File::open(".env").unwrap());
+    assert!(!lines.iter().any(|x| x.starts_with("KRS_BROKERS=")));
+    assert!(!lines.iter().any(|x| x.starts_with("KRS_GROUP_ID=")));
+    assert_some!(lines.iter().find(|x| x.starts_with("KRS_BROKERS_OLD=")));
+    assert!(UnsetCommand.run(&["not_a_key".to_owned()]).is_err());
+
     Ok(())
 }
@@ -0,0 +1,62 @@
+#[cfg(test)]
+use std::convert::TryFrom;
+
+use krs::errors::exit_code;
+use krs::{dispatch, make_parser, Config, Error};
+use rdkafka::error::{KafkaError, RDKafkaError};
+
+// TODO: https://github.com/rust-lang/rust/issues/46379
+mod util;
+pub use util::*;
+
+#[test]
+fn test_error_messages_and_exit_codes() {
+    let e = Error::InvalidUsage("brokers is required for `health`".into());
+    assert_eq!(e.to_string(), "brokers is required for `health`");
+    assert_eq!(e.exit_code(), exit_code::USAGE);
+
+    let e = Error::Kafka(KafkaError::MetadataFetch(RDKafkaError::OperationTimedOut));
+    assert!(e.to_string().starts_with("Kafka error: "));
+    assert_eq!(e.exit_code(), exit_code::TIMEOUT);
+
+    let cases = vec![
+        (RDKafkaError::SaslAuthenticationFailed, exit_code::AUTH),
+        (RDKafkaError::TopicAuthorizationFailed, exit_code::AUTH),
+        (RDKafkaError::UnknownTopicOrPartition, exit_code::NOT_FOUND),
+        (RDKafkaError::AllBrokersDown, exit_code::BROKER),
+    ];
+    for (code, expected) in cases {
+        assert_eq!(
+            Error::Kafka(KafkaError::AdminOp(code)).exit_code(),
+            expected,
+            "{:?}",
+            code
+        );
+    }
+    assert_eq!(
+        Error::Generic("Found 1 problem(s)".into()).exit_code(),
+        exit_code::FAILURE
+    );
+
+    // Properties rejected by librdkafka are usage errors.
+    let matches = make_parser()
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9092",
+            "-X",
+            "no.such.property=1",
+            "topics",
+            "list",
+        ])
+        .unwrap();
+    match krs::commands::topics::ListCommand::try_from(Config::from(&matches)) {
+        Err(e) => assert_eq!(e.exit_code(), exit_code::USAGE),
+        Ok(_) => panic!("Expected an error for an unknown property"),
+    }
+
+    let matches = make_parser()
+        .get_matches_from_safe(&["./binary", "topics", "config"])
+        .unwrap();
+    assert_eq!(dispatch(matches).unwrap_err().exit_code(), exit_code::USAGE);
+}
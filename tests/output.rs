@@ -0,0 +1,64 @@
+#[cfg(test)]
+use serde::Serialize;
+
+use krs::{make_parser, Config, OutputFormat};
+
+#[derive(Serialize)]
+struct Row {
+    name: &'static str,
+    num_partitions: usize,
+}
+
+fn rows() -> Vec<Row> {
+    vec![
+        Row {
+            name: "a-topic",
+            num_partitions: 1,
+        },
+        Row {
+            name: "another-topic",
+            num_partitions: 12,
+        },
+    ]
+}
+
+#[test]
+fn test_render_json() {
+    let actual = OutputFormat::Json.render(&rows()).unwrap();
+    assert_eq!(
+        actual,
+        r#"[{"name":"a-topic","num_partitions":1},{"name":"another-topic","num_partitions":12}]"#
+    );
+}
+
+#[test]
+fn test_render_table() {
+    let actual = OutputFormat::Table.render(&rows()).unwrap();
+    assert_eq!(
+        actual,
+        "NAME           NUM_PARTITIONS\n\
+         a-topic        1\n\
+         another-topic  12\n"
+    );
+}
+
+#[test]
+fn test_render_csv() {
+    let actual = OutputFormat::Csv.render(&rows()).unwrap();
+    assert_eq!(actual, "name,num_partitions\na-topic,1\nanother-topic,12\n");
+}
+
+#[test]
+fn test_output_arg() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&["./binary", "-o", "yaml", "env", "show"])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert_eq!(config.output, Some(OutputFormat::Yaml));
+
+    let parser = make_parser();
+    assert!(parser
+        .get_matches_from_safe(&["./binary", "-o", "xml", "env", "show"])
+        .is_err());
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+use std::convert::TryFrom;
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::c_char;
+
+use krs::security::{is_sensitive_property, Password};
+use krs::{make_parser, Config};
+use rdkafka::ClientConfig;
+use rdkafka_sys::RDKafkaConfRes;
+
+// TODO: https://github.com/rust-lang/rust/issues/46379
+mod util;
+pub use util::*;
+
+#[test]
+fn test_password_sources() {
+    let password = Password::Command("echo s3cret".into());
+    assert_eq!(password.resolve().unwrap(), "s3cret");
+    assert!(Password::Command("exit 1".into()).resolve().is_err());
+
+    let path = env::temp_dir().join(format!("krs-password-{}", rand::random::<u32>()));
+    fs::write(&path, "s3cret\n").unwrap();
+    let password = Password::File(path.to_string_lossy().into_owned());
+    assert_eq!(password.resolve().unwrap(), "s3cret");
+    fs::remove_file(&path).unwrap();
+    assert!(password.resolve().is_err());
+
+    assert!(!Password::Plain("s3cret".into()).masked().contains("s3cret"));
+}
+
+#[test]
+fn test_sasl_args() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "-b",
+            "localhost:9092",
+            "--sasl-mechanism",
+            "scram-sha-512",
+            "--sasl-username",
+            "me",
+            "--password-cmd",
+            "echo s3cret",
+            "topics",
+            "list",
+        ])
+        .unwrap();
+    let config = Config::from(&matches);
+    assert!(config.sasl.is_enabled());
+    assert_eq!(
+        config.sasl.mechanism.as_ref().unwrap().value,
+        "SCRAM-SHA-512"
+    );
+    assert_eq!(
+        config.sasl.password.as_ref().unwrap().value,
+        Password::Command("echo s3cret".into())
+    );
+    // Runs the password command and creates the clients.
+    assert_ok!(krs::commands::topics::ListCommand::try_from(config));
+
+    let parser = make_parser();
+    assert!(parser
+        .get_matches_from_safe(&["./binary", "--sasl-mechanism", "GSSAPI", "env", "show"])
+        .is_err());
+}
+
+// Reads a property back from the config that librdkafka would get.
+fn conf_value(config: &ClientConfig, key: &str) -> Option<String> {
+    let native = config.create_native_config().unwrap();
+    let key = CString::new(key).unwrap();
+    let mut buf = vec![0u8; 512];
+    let mut size = buf.len();
+    let res = unsafe {
+        rdkafka_sys::rd_kafka_conf_get(
+            native.ptr(),
+            key.as_ptr(),
+            buf.as_mut_ptr() as *mut c_char,
+            &mut size,
+        )
+    };
+    if res != RDKafkaConfRes::RD_KAFKA_CONF_OK {
+        return None;
+    }
+    Some(
+        CStr::from_bytes_with_nul(&buf[..size])
+            .unwrap()
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+#[test]
+fn test_oauthbearer_uses_unsecured_tokens() {
+    let parser = make_parser();
+    let matches = parser
+        .get_matches_from_safe(&[
+            "./binary",
+            "--sasl-mechanism",
+            "oauthbearer",
+            "--sasl-username",
+            "me",
+            "env",
+            "show",
+        ])
+        .unwrap();
+    let config = Config::from(&matches);
+    let mut client_config = ClientConfig::new();
+    assert_ok!(config.sasl.apply(&mut client_config));
+    assert_eq!(
+        conf_value(&client_config, "sasl.mechanisms"),
+        Some("OAUTHBEARER".to_owned())
+    );
+    assert_eq!(
+        conf_value(&client_config, "enable.sasl.oauthbearer.unsecure.jwt"),
+        Some("true".to_owned())
+    );
+    assert_eq!(
+        conf_value(&client_config, "sasl.oauthbearer.config"),
+        Some("principal=me".to_owned())
+    );
+}
+
+#[test]
+fn test_sensitive_properties() {
+    for key in &[
+        "ssl.key.pem",
+        "ssl.key.password",
+        "sasl.oauthbearer.config",
+        "consumer.sasl.password",
+        "admin.ssl.keystore.password",
+    ] {
+        assert!(is_sensitive_property(key), "{}", key);
+    }
+    for key in &[
+        "ssl.certificate.pem",
+        "client.id",
+        "consumer.fetch.max.bytes",
+    ] {
+        assert!(!is_sensitive_property(key), "{}", key);
+    }
+}
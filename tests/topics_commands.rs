@@ -3,8 +3,12 @@ use std::convert::TryFrom;
 use std::time::Duration;
 
 use rand::random;
+use regex::Regex;
 
-use krs::commands::topics::{CreateCommand, DeleteCommand, DescribeCommand, ListCommand};
+use krs::commands::topics::{
+    AddPartitionsCommand, ConfigGetCommand, ConfigSetCommand, ConfigUnsetCommand, CreateCommand,
+    CreateOptions, DeleteCommand, DescribeCommand, ListCommand, ListOptions, TopicSort,
+};
 
 // TODO: https://github.com/rust-lang/rust/issues/46379
 mod util;
@@ -14,7 +18,21 @@ pub use util::*;
 fn test_list_topics_ok() {
     // Assumes that a Kafka broker is running at localhost:9092
     let cmd = ListCommand::try_from(test_config()).unwrap();
-    assert_ok!(cmd.run());
+    assert_ok!(cmd.run(&Default::default()));
+}
+
+#[test]
+fn test_list_topics_with_filters_ok() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = ListCommand::try_from(test_config()).unwrap();
+    let options = ListOptions {
+        pattern: Some(Regex::new("^krs-topic-").unwrap()),
+        exclude_internal: true,
+        min_partitions: Some(1),
+        sort: Some(TopicSort::Partitions),
+        with_counts: true,
+    };
+    assert_ok!(cmd.run(&options));
 }
 
 #[test]
@@ -24,12 +42,25 @@ fn test_create_and_describe_topics() {
 
     let topic_name = format!("krs-topic-{}", random::<u64>());
 
-    assert_ok!(cmd.run(&topic_name, 1, 1));
+    assert_ok!(cmd.run(&topic_name, 1, 1, &Default::default()));
 
     let cmd = DescribeCommand::try_from(test_config()).unwrap();
     assert_ok!(cmd.run(&topic_name));
 }
 
+#[test]
+fn test_describe_topics_without_zookeeper() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = CreateCommand::try_from(test_config()).unwrap();
+
+    let topic_name = format!("krs-topic-{}", random::<u64>());
+
+    assert_ok!(cmd.run(&topic_name, 1, 1, &Default::default()));
+
+    let cmd = DescribeCommand::try_from(test_config_brokers_only()).unwrap();
+    assert_ok!(cmd.run(&topic_name));
+}
+
 #[test]
 fn test_create_and_delete_topics() {
     // Assumes that a Kafka broker is running at localhost:9092
@@ -37,11 +68,93 @@ fn test_create_and_delete_topics() {
 
     let topic_name = format!("krs-topic-{}", random::<u64>());
 
-    assert_ok!(cmd.run(&topic_name, 1, 1));
+    assert_ok!(cmd.run(&topic_name, 1, 1, &Default::default()));
 
     // Apparently topics aren't immediately available after creation.
     std::thread::sleep(Duration::from_secs(1));
 
     let cmd = DeleteCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&[&topic_name], None, true));
+}
+
+#[test]
+fn test_set_get_and_unset_topic_configs() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = CreateCommand::try_from(test_config()).unwrap();
+
+    let topic_name = format!("krs-topic-{}", random::<u64>());
+
+    assert_ok!(cmd.run(&topic_name, 1, 1, &Default::default()));
+
+    let cmd = ConfigSetCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&topic_name, &[("retention.ms".into(), "60000".into())]));
+
+    let cmd = ConfigGetCommand::try_from(test_config()).unwrap();
     assert_ok!(cmd.run(&topic_name));
+
+    let cmd = ConfigUnsetCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&topic_name, &["retention.ms".into()]));
+}
+
+#[test]
+fn test_create_topics_if_not_exists() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = CreateCommand::try_from(test_config()).unwrap();
+
+    let topic_name = format!("krs-topic-{}", random::<u64>());
+    let options = CreateOptions {
+        configs: vec![("retention.ms".into(), "60000".into())],
+        replica_assignment: Some(vec![vec![1], vec![1]]),
+        ..Default::default()
+    };
+
+    assert_ok!(cmd.run(&topic_name, 1, 1, &options));
+    assert!(cmd.run(&topic_name, 1, 1, &options).is_err());
+
+    let options = CreateOptions {
+        if_not_exists: true,
+        ..options
+    };
+    assert_ok!(cmd.run(&topic_name, 1, 1, &options));
+}
+
+#[test]
+fn test_add_partitions() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = CreateCommand::try_from(test_config()).unwrap();
+
+    let topic_name = format!("krs-topic-{}", random::<u64>());
+
+    assert_ok!(cmd.run(&topic_name, 1, 1, &Default::default()));
+
+    let cmd = AddPartitionsCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&topic_name, 2, None));
+    assert_ok!(cmd.run(&topic_name, 3, Some(&[vec![1]])));
+}
+
+#[test]
+fn test_create_and_delete_topics_by_regex() {
+    // Assumes that a Kafka broker is running at localhost:9092
+    let cmd = CreateCommand::try_from(test_config()).unwrap();
+
+    let prefix = format!("krs-topic-{}", random::<u64>());
+    for i in 0..3 {
+        assert_ok!(cmd.run(&format!("{}-{}", prefix, i), 1, 1, &Default::default()));
+    }
+    let other = format!("other-{}-0", prefix);
+    assert_ok!(cmd.run(&other, 1, 1, &Default::default()));
+
+    // Apparently topics aren't immediately available after creation.
+    std::thread::sleep(Duration::from_secs(1));
+
+    // The regex has to match the whole name, so `other-...` is kept.
+    let pattern = Regex::new(&format!("{}-.*", prefix)).unwrap();
+    let cmd = DeleteCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&[], Some(&pattern), true));
+
+    let cmd = DescribeCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&other));
+
+    let cmd = DeleteCommand::try_from(test_config()).unwrap();
+    assert_ok!(cmd.run(&[&other], None, true));
 }
@@ -5,11 +5,11 @@ use krs::{Config, Sourced};
 macro_rules! assert_ok {
     ($x:expr) => {
         if let Err(v) = $x {
-            panic!(format!(
+            panic!(
                 "In '{}', expected Ok value, but got {:?}",
                 stringify!($x),
                 v
-            ));
+            );
         }
     };
 }
@@ -18,10 +18,7 @@ macro_rules! assert_ok {
 macro_rules! assert_some {
     ($x:expr) => {
         if $x.is_none() {
-            panic!(format!(
-                "In '{}', expected Some value, but got None",
-                stringify!($x)
-            ));
+            panic!("In '{}', expected Some value, but got None", stringify!($x));
         }
     };
 }